@@ -0,0 +1,196 @@
+use proc_macro2::{Span, TokenStream};
+use quote::{format_ident, quote};
+use syn::{Attribute, Fields, Ident, Member, Result, Type};
+
+/// Attributes on the struct or enum
+#[derive(Default)]
+pub struct Container {
+    /// Encoded as its only field
+    pub transparent: bool,
+    /// Extra items at the end of the list are ignored when decoding
+    pub trailing: bool,
+}
+
+impl Container {
+    pub fn from_attrs(attrs: &[Attribute]) -> Result<Self> {
+        let mut container = Container::default();
+        for attr in attrs.iter().filter(|attr| attr.path().is_ident("rlp")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("transparent") {
+                    container.transparent = true;
+                } else if meta.path.is_ident("trailing") {
+                    container.trailing = true;
+                } else {
+                    return Err(meta.error("unknown rlp container attribute"));
+                }
+                Ok(())
+            })?;
+        }
+        Ok(container)
+    }
+}
+
+/// How a field is written to the list
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    /// Through its `Encodable` and `Decodable` impls
+    Plain,
+    /// Not encoded, decoded as `Default::default()`
+    Skip,
+    /// Byte string from `AsRef<[u8]>`, decoded through `From<Vec<u8>>`
+    Bytes,
+    /// Elements are items of the enclosing list
+    Tail,
+}
+
+pub struct Field {
+    /// `self.x` or `self.0`
+    pub member: Member,
+    /// Name the field is bound to in patterns and while decoding
+    pub binding: Ident,
+    /// Name used in error paths, the index for tuple fields
+    pub name: Option<String>,
+    pub index: usize,
+    pub kind: Kind,
+    /// Left out while `None`, no later optional field may be set then
+    pub optional: bool,
+    /// Decoded as `Default::default()` when the list ends before it
+    pub default: bool,
+}
+
+impl Field {
+    /// Parses the fields, `implicit_optional` marks trailing `Option` fields optional like the
+    /// serializer leaves out trailing `None` struct fields
+    pub fn parse_all(fields: &Fields, implicit_optional: bool) -> Result<Vec<Field>> {
+        let mut parsed = Vec::new();
+        let mut types = Vec::new();
+        for (index, field) in fields.iter().enumerate() {
+            let (member, binding, name) = match &field.ident {
+                Some(ident) => (
+                    Member::Named(ident.clone()),
+                    format_ident!("__field_{}", ident),
+                    Some(ident.to_string()),
+                ),
+                None => (
+                    Member::Unnamed(index.into()),
+                    format_ident!("__field{}", index),
+                    None,
+                ),
+            };
+            let mut kind = Kind::Plain;
+            let mut optional = false;
+            let mut default = false;
+            for attr in field
+                .attrs
+                .iter()
+                .filter(|attr| attr.path().is_ident("rlp"))
+            {
+                attr.parse_nested_meta(|meta| {
+                    let set = |kind: &mut Kind, new: Kind| {
+                        if *kind != Kind::Plain {
+                            return Err(meta.error("conflicting rlp field attributes"));
+                        }
+                        *kind = new;
+                        Ok(())
+                    };
+                    if meta.path.is_ident("skip") {
+                        set(&mut kind, Kind::Skip)?;
+                    } else if meta.path.is_ident("bytes") {
+                        set(&mut kind, Kind::Bytes)?;
+                    } else if meta.path.is_ident("tail") {
+                        set(&mut kind, Kind::Tail)?;
+                    } else if meta.path.is_ident("optional") {
+                        optional = true;
+                    } else if meta.path.is_ident("default") {
+                        default = true;
+                    } else {
+                        return Err(meta.error("unknown rlp field attribute"));
+                    }
+                    Ok(())
+                })?;
+            }
+            if optional && kind != Kind::Plain {
+                return Err(syn::Error::new_spanned(
+                    field,
+                    "optional fields cannot be skipped, bytes or a tail",
+                ));
+            }
+            parsed.push(Field {
+                member,
+                binding,
+                name,
+                index,
+                kind,
+                optional,
+                default,
+            });
+            types.push(&field.ty);
+        }
+
+        if implicit_optional {
+            for (field, ty) in parsed.iter_mut().zip(&types).rev() {
+                match field.kind {
+                    Kind::Skip => continue,
+                    Kind::Plain if is_option(ty) => field.optional = true,
+                    _ => break,
+                }
+            }
+        }
+        validate(&parsed, fields)?;
+        Ok(parsed)
+    }
+
+    /// Fields that are part of the encoding
+    pub fn encoded(fields: &[Field]) -> impl Iterator<Item = &Field> {
+        fields.iter().filter(|field| field.kind != Kind::Skip)
+    }
+
+    /// Segment of the error path for this field
+    pub fn segment(&self) -> TokenStream {
+        match &self.name {
+            Some(name) => quote!(::serde_rlp::error::Segment::Field(#name)),
+            None => {
+                let index = self.index;
+                quote!(::serde_rlp::error::Segment::Index(#index))
+            }
+        }
+    }
+}
+
+/// Optional fields have to be trailing and a tail has to be the last field
+fn validate(parsed: &[Field], fields: &Fields) -> Result<()> {
+    let encoded: Vec<_> = Field::encoded(parsed).collect();
+    let span = |field: &Field| {
+        fields
+            .iter()
+            .nth(field.index)
+            .map_or_else(Span::call_site, |field| {
+                syn::spanned::Spanned::span(&field.ty)
+            })
+    };
+    for (position, field) in encoded.iter().enumerate() {
+        let rest = &encoded[position + 1..];
+        if field.kind == Kind::Tail && !rest.is_empty() {
+            return Err(syn::Error::new(span(field), "tail must be the last field"));
+        }
+        if field.optional && rest.iter().any(|field| !field.optional) {
+            return Err(syn::Error::new(
+                span(field),
+                "optional fields may only be followed by optional fields",
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Whether the type is spelled as an `Option`, aliases and newtypes are not recognized
+fn is_option(ty: &Type) -> bool {
+    match ty {
+        Type::Path(path) if path.qself.is_none() => path
+            .path
+            .segments
+            .last()
+            .is_some_and(|segment| segment.ident == "Option"),
+        _ => false,
+    }
+}
@@ -0,0 +1,504 @@
+//! Derives `Encodable` and `Decodable` from `serde_rlp`
+//!
+//! Structs are a list of their fields and single field tuple structs are transparent, the same
+//! as `RlpSerializer` encodes them. Unit enum variants are their index, other variants a list
+//! of the index followed by the fields.
+//!
+//! Container attributes:
+//! - `#[rlp(transparent)]` encodes a struct as its only field
+//! - `#[rlp(trailing)]` ignores extra items at the end of the list when decoding
+//!
+//! Field attributes:
+//! - `#[rlp(skip)]` leaves the field out, it decodes as `Default::default()`
+//! - `#[rlp(default)]` decodes as `Default::default()` when the list ends before the field
+//! - `#[rlp(optional)]` leaves the field out while it is `None`, trailing `Option` fields of
+//!   structs are optional without the attribute. Encoding panics when an optional field is set
+//!   after one that is `None`, where `RlpSerializer` returns an error
+//! - `#[rlp(tail)]` spreads the elements of the last field over the rest of the list
+//! - `#[rlp(bytes)]` encodes the field as a byte string instead of a list of bytes
+//!
+//! Only fields spelled `Option<..>` are implicitly optional, the macro cannot see through a type
+//! alias or a newtype. `RlpSerializer` decides at runtime instead and leaves out any trailing
+//! `None`, so mark aliases of `Option` with `#[rlp(optional)]` to get the same bytes. A newtype
+//! over `Option` cannot be optional here and differs from the serializer when it is a trailing
+//! `None`.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{parse_macro_input, parse_quote, Data, DeriveInput, Fields, Generics, Result};
+
+mod attr;
+
+use attr::{Container, Field, Kind};
+
+#[proc_macro_derive(RlpEncodable, attributes(rlp))]
+pub fn derive_encodable(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_encodable(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+#[proc_macro_derive(RlpDecodable, attributes(rlp))]
+pub fn derive_decodable(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_decodable(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// How the type is laid out in the encoding
+enum Shape {
+    /// Encoded as its only encoded field
+    Transparent(Vec<Field>),
+    /// List of the fields
+    Struct(Vec<Field>),
+    Enum(Vec<Variant>),
+}
+
+struct Variant {
+    ident: syn::Ident,
+    index: u32,
+    unit: bool,
+    fields: Vec<Field>,
+}
+
+fn parse_shape(input: &DeriveInput, container: &Container) -> Result<Shape> {
+    match &input.data {
+        Data::Struct(data) => {
+            let newtype =
+                matches!(&data.fields, Fields::Unnamed(fields) if fields.unnamed.len() == 1);
+            let fields = Field::parse_all(&data.fields, !container.transparent && !newtype)?;
+            if !container.transparent && !newtype {
+                return Ok(Shape::Struct(fields));
+            }
+            let encoded: Vec<_> = Field::encoded(&fields).collect();
+            match encoded[..] {
+                [field] if field.kind != Kind::Tail && !field.optional => {
+                    Ok(Shape::Transparent(fields))
+                }
+                _ => Err(syn::Error::new_spanned(
+                    &input.ident,
+                    "transparent structs need exactly one field that is not skipped, optional or a tail",
+                )),
+            }
+        }
+        Data::Enum(data) => {
+            if container.transparent {
+                return Err(syn::Error::new_spanned(
+                    &input.ident,
+                    "enums cannot be transparent",
+                ));
+            }
+            let variants = data
+                .variants
+                .iter()
+                .enumerate()
+                .map(|(index, variant)| {
+                    Ok(Variant {
+                        ident: variant.ident.clone(),
+                        index: index as u32,
+                        unit: matches!(variant.fields, Fields::Unit),
+                        fields: Field::parse_all(&variant.fields, false)?,
+                    })
+                })
+                .collect::<Result<_>>()?;
+            Ok(Shape::Enum(variants))
+        }
+        Data::Union(_) => Err(syn::Error::new_spanned(
+            &input.ident,
+            "unions are not supported",
+        )),
+    }
+}
+
+/// Requires `bound` of every type parameter
+fn add_bounds(generics: &Generics, bound: TokenStream2) -> Generics {
+    let mut generics = generics.clone();
+    let params: Vec<_> = generics
+        .type_params()
+        .map(|param| param.ident.clone())
+        .collect();
+    let where_clause = generics.make_where_clause();
+    for param in params {
+        where_clause.predicates.push(parse_quote!(#param: #bound));
+    }
+    generics
+}
+
+/// Pattern binding the encoded fields by reference
+fn pattern(path: TokenStream2, fields: &[Field]) -> TokenStream2 {
+    let bindings = Field::encoded(fields).map(|field| {
+        let member = &field.member;
+        let binding = &field.binding;
+        quote!(#member: #binding)
+    });
+    quote!(#path { #(#bindings,)* .. })
+}
+
+fn expand_encodable(input: &DeriveInput) -> Result<TokenStream2> {
+    let container = Container::from_attrs(&input.attrs)?;
+    let shape = parse_shape(input, &container)?;
+    let name = &input.ident;
+    let generics = add_bounds(&input.generics, quote!(::serde_rlp::encode::Encodable));
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let (encode, length) = match &shape {
+        Shape::Transparent(fields) => {
+            let pattern = pattern(quote!(Self), fields);
+            let field = Field::encoded(fields).next().unwrap();
+            let write = write_field(field);
+            let length = field_length(field);
+            (
+                quote! {
+                    let #pattern = self;
+                    #write
+                },
+                quote! {
+                    let #pattern = self;
+                    #length
+                },
+            )
+        }
+        Shape::Struct(fields) => {
+            let pattern = pattern(quote!(Self), fields);
+            let (prelude, writes) = encode_list(fields, None);
+            let list_length = list_length();
+            (
+                quote! {
+                    let #pattern = self;
+                    #prelude
+                    #writes
+                },
+                quote! {
+                    let #pattern = self;
+                    #prelude
+                    #list_length
+                },
+            )
+        }
+        Shape::Enum(variants) => {
+            let mut encode_arms = Vec::new();
+            let mut length_arms = Vec::new();
+            for variant in variants {
+                let ident = &variant.ident;
+                let index = variant.index;
+                let pattern = pattern(quote!(Self::#ident), &variant.fields);
+                if variant.unit {
+                    encode_arms.push(quote! {
+                        #pattern => ::serde_rlp::encode::Encodable::encode(&#index, out),
+                    });
+                    length_arms.push(quote! {
+                        #pattern => ::serde_rlp::encode::Encodable::length(&#index),
+                    });
+                } else {
+                    let (prelude, writes) = encode_list(&variant.fields, Some(index));
+                    let list_length = list_length();
+                    encode_arms.push(quote! {
+                        #pattern => {
+                            #prelude
+                            #writes
+                        }
+                    });
+                    length_arms.push(quote! {
+                        #pattern => {
+                            #prelude
+                            #list_length
+                        }
+                    });
+                }
+            }
+            (
+                quote!(match self { #(#encode_arms)* }),
+                quote!(match self { #(#length_arms)* }),
+            )
+        }
+    };
+
+    Ok(quote! {
+        impl #impl_generics ::serde_rlp::encode::Encodable for #name #ty_generics #where_clause {
+            fn encode(&self, out: &mut dyn ::serde_rlp::encode::BufMut) {
+                #encode
+            }
+
+            fn length(&self) -> usize {
+                #length
+            }
+        }
+    })
+}
+
+/// Length of a list whose payload length is in `__payload_length`
+fn list_length() -> TokenStream2 {
+    quote! {
+        __payload_length
+            + ::serde_rlp::header::Header {
+                list: true,
+                payload_len: __payload_length,
+            }
+            .length()
+    }
+}
+
+/// Statements computing `__payload_length` and the ones writing the list
+/// `index` is the enum variant index written before the fields
+fn encode_list(fields: &[Field], index: Option<u32>) -> (TokenStream2, TokenStream2) {
+    let encoded: Vec<_> = Field::encoded(fields).collect();
+
+    // Optional fields are written while set, a set one after a `None` one panics like the
+    // serializer errors on it
+    let optional: Vec<_> = encoded.iter().filter(|field| field.optional).collect();
+    let checks = optional.iter().map(|field| {
+        let binding = &field.binding;
+        let name = field
+            .name
+            .clone()
+            .unwrap_or_else(|| field.index.to_string());
+        quote! {
+            if ::core::option::Option::is_none(#binding) {
+                __none_field.get_or_insert(#name);
+            } else if let ::core::option::Option::Some(__none) = __none_field {
+                panic!("field `{}` is set after optional field `{}` is None", #name, __none);
+            }
+        }
+    });
+    let checks = if optional.is_empty() {
+        quote!()
+    } else {
+        quote! {
+            let mut __none_field: ::core::option::Option<&'static str> =
+                ::core::option::Option::None;
+            #(#checks)*
+        }
+    };
+
+    let lengths = encoded.iter().map(|field| {
+        let length = field_length(field);
+        if field.optional {
+            let binding = &field.binding;
+            quote!(if ::core::option::Option::is_some(#binding) { #length } else { 0 })
+        } else {
+            length
+        }
+    });
+    let writes = encoded.iter().map(|field| {
+        let write = write_field(field);
+        if field.optional {
+            let binding = &field.binding;
+            quote!(if ::core::option::Option::is_some(#binding) { #write })
+        } else {
+            write
+        }
+    });
+    let (index_length, index_write) = match index {
+        Some(index) => (
+            quote!(::serde_rlp::encode::Encodable::length(&#index)),
+            quote!(::serde_rlp::encode::Encodable::encode(&#index, out);),
+        ),
+        None => (quote!(0usize), quote!()),
+    };
+
+    let prelude = quote! {
+        #checks
+        let __payload_length: usize = #index_length #(+ #lengths)*;
+    };
+    let writes = quote! {
+        ::serde_rlp::header::Header {
+            list: true,
+            payload_len: __payload_length,
+        }
+        .encode(out);
+        #index_write
+        #(#writes;)*
+    };
+    (prelude, writes)
+}
+
+fn field_length(field: &Field) -> TokenStream2 {
+    let binding = &field.binding;
+    match field.kind {
+        Kind::Plain => quote!(::serde_rlp::encode::Encodable::length(#binding)),
+        Kind::Bytes => quote! {
+            ::serde_rlp::encode::bytes_length(::core::convert::AsRef::<[u8]>::as_ref(#binding))
+        },
+        Kind::Tail => quote! {
+            #binding
+                .iter()
+                .map(::serde_rlp::encode::Encodable::length)
+                .sum::<usize>()
+        },
+        Kind::Skip => quote!(0),
+    }
+}
+
+fn write_field(field: &Field) -> TokenStream2 {
+    let binding = &field.binding;
+    match field.kind {
+        Kind::Plain => quote!(::serde_rlp::encode::Encodable::encode(#binding, out)),
+        Kind::Bytes => quote! {
+            ::serde_rlp::encode::encode_bytes(::core::convert::AsRef::<[u8]>::as_ref(#binding), out)
+        },
+        Kind::Tail => quote! {
+            for item in #binding.iter() {
+                ::serde_rlp::encode::Encodable::encode(item, out);
+            }
+        },
+        Kind::Skip => quote!(),
+    }
+}
+
+fn expand_decodable(input: &DeriveInput) -> Result<TokenStream2> {
+    let container = Container::from_attrs(&input.attrs)?;
+    let shape = parse_shape(input, &container)?;
+    let name = &input.ident;
+    let generics = add_bounds(&input.generics, quote!(::serde_rlp::decode::Decodable));
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let body = match &shape {
+        Shape::Transparent(fields) => {
+            let values = fields.iter().map(|field| {
+                let member = &field.member;
+                let value = match field.kind {
+                    Kind::Skip => quote!(::core::default::Default::default()),
+                    _ => {
+                        let read = read_field(field, quote!(buf));
+                        quote!(#read?)
+                    }
+                };
+                quote!(#member: #value)
+            });
+            quote!(::core::result::Result::Ok(Self { #(#values,)* }))
+        }
+        Shape::Struct(fields) => {
+            let decode = decode_fields(quote!(Self), fields, &container);
+            quote! {
+                #[allow(unused_mut)]
+                let (mut __payload, __rest) = ::serde_rlp::header::split_list(buf)?;
+                let __value = { #decode };
+                *buf = __rest;
+                ::core::result::Result::Ok(__value)
+            }
+        }
+        Shape::Enum(variants) => {
+            let unit_arms = variants
+                .iter()
+                .filter(|variant| variant.unit)
+                .map(|variant| {
+                    let ident = &variant.ident;
+                    let index = variant.index;
+                    quote!(#index => ::core::result::Result::Ok(Self::#ident),)
+                });
+            let list_arms = variants.iter().map(|variant| {
+                let ident = &variant.ident;
+                let index = variant.index;
+                let decode = decode_fields(quote!(Self::#ident), &variant.fields, &container);
+                quote!(#index => { #decode })
+            });
+            quote! {
+                let mut __peek = *buf;
+                let __header = ::serde_rlp::header::Header::decode(&mut __peek)?;
+                if !__header.list {
+                    let __index = <u32 as ::serde_rlp::decode::Decodable>::decode(buf)?;
+                    return match __index {
+                        #(#unit_arms)*
+                        _ => ::core::result::Result::Err(::serde_rlp::decode::unknown_variant(__index)),
+                    };
+                }
+                let (mut __payload, __rest) = ::serde_rlp::header::split_list(buf)?;
+                let __index = <u32 as ::serde_rlp::decode::Decodable>::decode(&mut __payload)?;
+                let __value = match __index {
+                    #(#list_arms)*
+                    _ => return ::core::result::Result::Err(::serde_rlp::decode::unknown_variant(__index)),
+                };
+                *buf = __rest;
+                ::core::result::Result::Ok(__value)
+            }
+        }
+    };
+
+    Ok(quote! {
+        impl #impl_generics ::serde_rlp::decode::Decodable for #name #ty_generics #where_clause {
+            fn decode(buf: &mut &[u8]) -> ::core::result::Result<Self, ::serde_rlp::error::Error> {
+                #body
+            }
+        }
+    })
+}
+
+/// Block reading the fields from `__payload` and building `path` from them
+fn decode_fields(path: TokenStream2, fields: &[Field], container: &Container) -> TokenStream2 {
+    let count = !container.trailing;
+    let reads = fields.iter().map(|field| {
+        let binding = &field.binding;
+        if field.kind == Kind::Skip {
+            return quote!(let #binding = ::core::default::Default::default(););
+        }
+        let read = read_field(field, quote!(&mut __payload));
+        let segment = field.segment();
+        let counted = count.then(|| quote!(__consumed += 1;));
+        let read = quote! {{
+            let __field = #read.map_err(|err| err.within(#segment))?;
+            #counted
+            __field
+        }};
+        if field.optional || field.default {
+            quote! {
+                let #binding = if __payload.is_empty() {
+                    ::core::default::Default::default()
+                } else #read;
+            }
+        } else {
+            quote!(let #binding = #read;)
+        }
+    });
+    let members = fields.iter().map(|field| {
+        let member = &field.member;
+        let binding = &field.binding;
+        quote!(#member: #binding)
+    });
+    let (counter, check) = if count {
+        (
+            quote! {
+                #[allow(unused_mut)]
+                let mut __consumed = 0usize;
+            },
+            quote! {
+                if !__payload.is_empty() {
+                    return ::core::result::Result::Err(
+                        ::serde_rlp::error::Error::TrailingItems(__consumed),
+                    );
+                }
+            },
+        )
+    } else {
+        (quote!(), quote!())
+    };
+    quote! {
+        #counter
+        #(#reads)*
+        #check
+        #path { #(#members,)* }
+    }
+}
+
+/// Expression decoding the field from `buf`, evaluates to a `Result`
+fn read_field(field: &Field, buf: TokenStream2) -> TokenStream2 {
+    match field.kind {
+        Kind::Plain | Kind::Skip => quote!(::serde_rlp::decode::Decodable::decode(#buf)),
+        Kind::Bytes => quote! {
+            ::serde_rlp::decode::next_string(#buf)
+                .map(|bytes| ::core::convert::From::from(bytes.to_vec()))
+        },
+        Kind::Tail => quote! {
+            ::core::iter::from_fn(|| {
+                if __payload.is_empty() {
+                    ::core::option::Option::None
+                } else {
+                    ::core::option::Option::Some(::serde_rlp::decode::Decodable::decode(#buf))
+                }
+            })
+            .collect::<::core::result::Result<_, ::serde_rlp::error::Error>>()
+        },
+    }
+}
@@ -0,0 +1,219 @@
+use serde::{Deserialize, Serialize};
+use serde_rlp::decode::decode_exact;
+use serde_rlp::des::from_rlp_bytes;
+use serde_rlp::encode::{encode, Encodable};
+use serde_rlp::error::Error;
+use serde_rlp::ser::to_rlp_bytes;
+use serde_rlp::tail::Tail;
+use serde_rlp_derive::{RlpDecodable, RlpEncodable};
+
+#[derive(RlpEncodable, RlpDecodable, Serialize, Deserialize, Debug, PartialEq)]
+struct Header {
+    number: u64,
+    name: String,
+    base_fee: Option<u64>,
+    withdrawals_root: Option<Vec<u8>>,
+}
+
+#[derive(RlpEncodable, RlpDecodable, Serialize, Deserialize, Debug, PartialEq)]
+struct Id(u64);
+
+#[derive(RlpEncodable, RlpDecodable, Serialize, Deserialize, Debug, PartialEq)]
+enum Message {
+    Ping,
+    Hello(Id),
+    Status { version: u8, head: Header },
+    Pair(u32, String),
+}
+
+#[derive(RlpEncodable, RlpDecodable, Debug, PartialEq)]
+struct Versioned {
+    version: u8,
+    #[rlp(tail)]
+    rest: Vec<u64>,
+}
+
+/// The `Option` fields are not trailing, so they are plain fields
+#[derive(RlpEncodable, RlpDecodable, Serialize, Deserialize, Debug, PartialEq)]
+struct Mid {
+    a: Option<u8>,
+    b: Option<u8>,
+    c: u8,
+}
+
+#[derive(Serialize)]
+struct SerdeVersioned {
+    version: u8,
+    rest: Tail<u64>,
+}
+
+#[test]
+fn serde_identity_test() {
+    let headers = [
+        Header {
+            number: 5,
+            name: String::from("cat"),
+            base_fee: None,
+            withdrawals_root: None,
+        },
+        Header {
+            number: 1024,
+            name: String::new(),
+            base_fee: Some(1000),
+            withdrawals_root: Some(vec![1, 2]),
+        },
+    ];
+    for header in &headers {
+        let bytes = encode(header);
+        assert_eq!(bytes, to_rlp_bytes(header).unwrap());
+        assert_eq!(bytes.len(), header.length());
+        assert_eq!(&decode_exact::<Header>(&bytes).unwrap(), header);
+        assert_eq!(&from_rlp_bytes::<Header>(&bytes).unwrap(), header);
+    }
+
+    let mid = Mid {
+        a: None,
+        b: Some(1),
+        c: 2,
+    };
+    let bytes = encode(&mid);
+    assert_eq!(bytes, [0xc3, 0xc0, 0x01, 0x02]);
+    assert_eq!(bytes, to_rlp_bytes(&mid).unwrap());
+    assert_eq!(bytes.len(), mid.length());
+    assert_eq!(decode_exact::<Mid>(&bytes).unwrap(), mid);
+
+    // Both reject a set trailing optional field after a `None` one
+    let invalid = Header {
+        number: 5,
+        name: String::new(),
+        base_fee: None,
+        withdrawals_root: Some(vec![]),
+    };
+    assert!(to_rlp_bytes(&invalid).is_err());
+    assert!(std::panic::catch_unwind(|| encode(&invalid)).is_err());
+
+    let messages = [
+        Message::Ping,
+        Message::Hello(Id(7)),
+        Message::Status {
+            version: 66,
+            head: Header {
+                number: 1,
+                name: String::from("dog"),
+                base_fee: Some(7),
+                withdrawals_root: None,
+            },
+        },
+        Message::Pair(300, String::from("x")),
+    ];
+    for message in &messages {
+        let bytes = encode(message);
+        assert_eq!(bytes, to_rlp_bytes(message).unwrap());
+        assert_eq!(bytes.len(), message.length());
+        assert_eq!(&decode_exact::<Message>(&bytes).unwrap(), message);
+        assert_eq!(&from_rlp_bytes::<Message>(&bytes).unwrap(), message);
+    }
+    assert_eq!(encode(&Message::Ping), [0x80]);
+    assert_eq!(encode(&Message::Hello(Id(7))), [0xc2, 0x01, 0x07]);
+
+    let versioned = Versioned {
+        version: 1,
+        rest: vec![2, 1024],
+    };
+    let serde_versioned = SerdeVersioned {
+        version: 1,
+        rest: Tail(vec![2, 1024]),
+    };
+    let bytes = encode(&versioned);
+    assert_eq!(bytes, to_rlp_bytes(&serde_versioned).unwrap());
+    assert_eq!(decode_exact::<Versioned>(&bytes).unwrap(), versioned);
+}
+
+#[derive(RlpEncodable, RlpDecodable, Debug, PartialEq)]
+#[rlp(trailing)]
+struct Record {
+    #[rlp(bytes)]
+    data: Vec<u8>,
+    #[rlp(skip)]
+    cached: Option<u64>,
+    #[rlp(default)]
+    nonce: u64,
+}
+
+#[derive(RlpEncodable, RlpDecodable, Debug, PartialEq)]
+#[rlp(transparent)]
+struct Hash {
+    #[rlp(bytes)]
+    bytes: Vec<u8>,
+}
+
+#[derive(RlpEncodable, RlpDecodable, Debug, PartialEq)]
+struct Strict {
+    a: u8,
+    #[rlp(optional)]
+    b: Option<u8>,
+    #[rlp(optional)]
+    c: Option<u8>,
+}
+
+#[test]
+fn attributes_test() {
+    let record = Record {
+        data: vec![0xaa, 0xbb],
+        cached: Some(1),
+        nonce: 9,
+    };
+    assert_eq!(encode(&record), [0xc4, 0x82, 0xaa, 0xbb, 0x09]);
+    assert_eq!(
+        decode_exact::<Record>(&[0xc4, 0x82, 0xaa, 0xbb, 0x09]).unwrap(),
+        Record {
+            cached: None,
+            ..record
+        }
+    );
+    // Missing default field and extra trailing items
+    assert_eq!(
+        decode_exact::<Record>(&[0xc3, 0x82, 0xaa, 0xbb])
+            .unwrap()
+            .nonce,
+        0
+    );
+    assert_eq!(
+        decode_exact::<Record>(&[0xc6, 0x82, 0xaa, 0xbb, 0x09, 0x01, 0x02])
+            .unwrap()
+            .nonce,
+        9
+    );
+
+    let hash = Hash {
+        bytes: vec![0x12; 3],
+    };
+    assert_eq!(encode(&hash), [0x83, 0x12, 0x12, 0x12]);
+    assert_eq!(decode_exact::<Hash>(&encode(&hash)).unwrap(), hash);
+
+    let strict = Strict {
+        a: 1,
+        b: Some(2),
+        c: None,
+    };
+    assert_eq!(encode(&strict), [0xc2, 0x01, 0x02]);
+    assert_eq!(decode_exact::<Strict>(&encode(&strict)).unwrap(), strict);
+
+    // A set optional field after a `None` one is rejected, as by the serializer
+    let strict = Strict {
+        a: 1,
+        b: None,
+        c: Some(3),
+    };
+    let panic = std::panic::catch_unwind(|| encode(&strict)).unwrap_err();
+    assert_eq!(
+        panic.downcast_ref::<String>().unwrap(),
+        "field `c` is set after optional field `b` is None"
+    );
+    assert!(std::panic::catch_unwind(|| strict.length()).is_err());
+
+    let err = decode_exact::<Strict>(&[0xc4, 0x01, 0x02, 0x03, 0x04]).unwrap_err();
+    assert!(matches!(err, Error::TrailingItems(3)));
+    let err = decode_exact::<Header>(&[0xc2, 0x01, 0xc0]).unwrap_err();
+    assert_eq!(err.path().unwrap().to_string(), "name");
+}
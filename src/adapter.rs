@@ -0,0 +1,163 @@
+use core::fmt;
+use core::marker::PhantomData;
+
+use serde::de::{Deserialize, DeserializeOwned, Deserializer, Visitor};
+use serde::ser::{Serialize, Serializer};
+
+use crate::decode::{decode_exact, next_raw, Decodable};
+use crate::des::from_rlp_bytes;
+use crate::encode::{encode, BufMut, Encodable};
+use crate::error::Error;
+use crate::raw::{self, Verbatim};
+use crate::ser::{encoded_len, to_rlp_bytes};
+
+/// Embeds an `Encodable` / `Decodable` type in serde types
+///
+/// The native encoding goes through the serde data model like a `RawRlp`, so `to_rlp_bytes`
+/// writes it verbatim and `from_rlp_bytes` hands the next item to `Decodable` as is.
+/// Other formats see it as a byte string holding the encoding.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Native<T>(pub T);
+
+/// Uses a serde type where `Encodable` / `Decodable` is expected
+///
+/// # Panics
+///
+/// `Encodable` cannot fail, so `encode` and `length` panic when `to_rlp_bytes` returns an error
+/// for the value. That covers maps, tuples and arrays such as `[u8; 32]`, `u128`, a `Some`
+/// after a `None` among trailing optional fields and one-shot values like `iter_list`.
+/// Check such types with `to_rlp_bytes` first, or keep them out of `Serde`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Serde<T>(pub T);
+
+impl<T: Encodable> Serialize for Native<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_newtype_struct(raw::NAME, &Verbatim(&encode(&self.0)))
+    }
+}
+
+/// Decodes the bytes of the next item, as `RlpDeserializer` hands them to `RawRlp`
+struct NativeVisitor<T>(PhantomData<T>);
+
+impl<'de, T: Decodable> Visitor<'de> for NativeVisitor<T> {
+    type Value = Native<T>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("an encoded RLP item")
+    }
+
+    fn visit_newtype_struct<D: Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> Result<Native<T>, D::Error> {
+        deserializer.deserialize_bytes(self)
+    }
+
+    fn visit_bytes<E: serde::de::Error>(self, v: &[u8]) -> Result<Native<T>, E> {
+        decode_exact(v).map(Native).map_err(E::custom)
+    }
+}
+
+impl<'de, T: Decodable> Deserialize<'de> for Native<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_newtype_struct(raw::NAME, NativeVisitor(PhantomData))
+    }
+}
+
+impl<T: Serialize> Encodable for Serde<T> {
+    /// Panics if `T` does not serialize to RLP
+    fn encode(&self, out: &mut dyn BufMut) {
+        let bytes = to_rlp_bytes(&self.0).expect("value does not serialize to RLP");
+        out.put_slice(&bytes);
+    }
+
+    fn length(&self) -> usize {
+        encoded_len(&self.0).expect("value does not serialize to RLP")
+    }
+}
+
+impl<T: DeserializeOwned> Decodable for Serde<T> {
+    fn decode(buf: &mut &[u8]) -> Result<Self, Error> {
+        from_rlp_bytes(next_raw(buf)?).map(Serde)
+    }
+}
+
+/// `#[serde(with = "serde_rlp::adapter::native")]` for `Encodable` / `Decodable` fields
+pub mod native {
+    use super::*;
+
+    pub fn serialize<T, S>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        T: Encodable,
+        S: Serializer,
+    {
+        Native(value).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+    where
+        T: Decodable,
+        D: Deserializer<'de>,
+    {
+        Native::deserialize(deserializer).map(|native: Native<T>| native.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::{Deserialize, Serialize};
+
+    /// Hand written encoding, a point as a two byte string
+    #[derive(Debug, PartialEq)]
+    struct Point(u8, u8);
+
+    impl Encodable for Point {
+        fn encode(&self, out: &mut dyn BufMut) {
+            [self.0, self.1].encode(out);
+        }
+    }
+
+    impl Decodable for Point {
+        fn decode(buf: &mut &[u8]) -> Result<Self, Error> {
+            let [x, y] = <[u8; 2]>::decode(buf)?;
+            Ok(Point(x, y))
+        }
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Shape {
+        name: String,
+        #[serde(with = "native")]
+        origin: Point,
+        corners: Vec<Native<Point>>,
+    }
+
+    #[test]
+    fn adapter_test() {
+        let shape = Shape {
+            name: String::from("square"),
+            origin: Point(1, 2),
+            corners: vec![Native(Point(3, 4))],
+        };
+
+        let bytes = to_rlp_bytes(&shape).unwrap();
+        assert_eq!(&bytes[8..11], [0x82, 0x01, 0x02]);
+        assert_eq!(from_rlp_bytes::<Shape>(&bytes).unwrap(), shape);
+        assert_eq!(
+            crate::ser::to_array::<32, _>(&shape).unwrap().as_slice(),
+            &bytes[..]
+        );
+        // Decoding errors of the native type come through
+        assert!(from_rlp_bytes::<Native<Point>>(&[0x83, 0x01, 0x02, 0x03]).is_err());
+
+        let wrapped = Serde(shape);
+        assert_eq!(encode(&wrapped), bytes);
+        assert_eq!(wrapped.length(), bytes.len());
+        assert_eq!(decode_exact::<Serde<Shape>>(&bytes).unwrap(), wrapped);
+
+        // Values the serializer rejects panic, `Encodable` has no error to return
+        assert!(std::panic::catch_unwind(|| encode(&Serde(1u128))).is_err());
+        assert!(std::panic::catch_unwind(|| Serde(1u128).length()).is_err());
+    }
+}
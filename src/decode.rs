@@ -0,0 +1,212 @@
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use crate::error::Error;
+use crate::header::{split, split_list, split_string};
+
+/// Types decoding themselves straight from the encoded bytes, without going through serde
+pub trait Decodable: Sized {
+    /// Decodes one item from the front of `buf` and advances past it
+    fn decode(buf: &mut &[u8]) -> Result<Self, Error>;
+}
+
+/// Decodes a `T` from `bytes`, which must hold exactly one item
+pub fn decode_exact<T: Decodable>(mut bytes: &[u8]) -> Result<T, Error> {
+    let value = T::decode(&mut bytes)?;
+    if !bytes.is_empty() {
+        return Err(Error::TrailingItems(1));
+    }
+    Ok(value)
+}
+
+/// Splits the next byte string off `buf` and returns its payload
+pub fn next_string<'a>(buf: &mut &'a [u8]) -> Result<&'a [u8], Error> {
+    let (payload, rest) = split_string(buf)?;
+    *buf = rest;
+    Ok(payload)
+}
+
+/// Big endian integer of at most 16 bytes
+fn decode_u128(buf: &mut &[u8]) -> Result<u128, Error> {
+    let payload = next_string(buf)?;
+    if payload.len() > 16 {
+        return Err(Error::IntegerOverflow);
+    }
+    if payload.first() == Some(&0) {
+        return Err(Error::NonCanonical("integer with leading zero bytes"));
+    }
+    Ok(payload.iter().fold(0u128, |acc, &b| (acc << 8) | b as u128))
+}
+
+macro_rules! decodable_int {
+    ($($inttype:ty),*) => {
+        $(
+            impl Decodable for $inttype {
+                fn decode(buf: &mut &[u8]) -> Result<Self, Error> {
+                    <$inttype>::try_from(decode_u128(buf)?).map_err(|_| Error::IntegerOverflow)
+                }
+            }
+        )*
+    };
+}
+
+decodable_int!(u8, u16, u32, u64, u128, usize);
+
+// Signed values are decoded from their two's complement `u64` form
+macro_rules! decodable_signed_int {
+    ($($inttype:ty),*) => {
+        $(
+            impl Decodable for $inttype {
+                fn decode(buf: &mut &[u8]) -> Result<Self, Error> {
+                    let num = u64::try_from(decode_u128(buf)?).map_err(|_| Error::IntegerOverflow)?;
+                    <$inttype>::try_from(num as i64).map_err(|_| Error::IntegerOverflow)
+                }
+            }
+        )*
+    };
+}
+
+decodable_signed_int!(i8, i16, i32, i64, isize);
+
+impl Decodable for bool {
+    fn decode(buf: &mut &[u8]) -> Result<Self, Error> {
+        match next_string(buf)? {
+            [0x01] => Ok(true),
+            [] => Ok(false),
+            _ => Err(Error::Message("invalid boolean".into())),
+        }
+    }
+}
+
+impl Decodable for char {
+    fn decode(buf: &mut &[u8]) -> Result<Self, Error> {
+        let mut chars = core::str::from_utf8(next_string(buf)?)
+            .map_err(|_| Error::Message("invalid char".into()))?
+            .chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => Ok(c),
+            _ => Err(Error::Message("invalid char".into())),
+        }
+    }
+}
+
+impl Decodable for String {
+    fn decode(buf: &mut &[u8]) -> Result<Self, Error> {
+        let bytes = next_string(buf)?;
+        String::from_utf8(bytes.to_vec()).map_err(|err| Error::Message(err.to_string()))
+    }
+}
+
+/// Byte strings of exactly `N` bytes, arrays of other element types are left out as for `Encodable`
+impl<const N: usize> Decodable for [u8; N] {
+    fn decode(buf: &mut &[u8]) -> Result<Self, Error> {
+        next_string(buf)?
+            .try_into()
+            .map_err(|_| Error::Message(format!("expected {}-byte string", N)))
+    }
+}
+
+impl<T: Decodable> Decodable for Vec<T> {
+    fn decode(buf: &mut &[u8]) -> Result<Self, Error> {
+        let (mut payload, rest) = split_list(buf)?;
+        *buf = rest;
+
+        let mut items = Vec::new();
+        while !payload.is_empty() {
+            items.push(T::decode(&mut payload)?);
+        }
+        Ok(items)
+    }
+}
+
+/// An empty list is `None`, like `RlpDeserializer` outside of struct fields
+impl<T: Decodable> Decodable for Option<T> {
+    fn decode(buf: &mut &[u8]) -> Result<Self, Error> {
+        if buf.first() == Some(&0xc0) {
+            *buf = &buf[1..];
+            return Ok(None);
+        }
+        T::decode(buf).map(Some)
+    }
+}
+
+impl<T: Decodable> Decodable for Box<T> {
+    fn decode(buf: &mut &[u8]) -> Result<Self, Error> {
+        T::decode(buf).map(Box::new)
+    }
+}
+
+macro_rules! decodable_tuple {
+    ($len:expr; $($name:ident),+) => {
+        /// Tuples are lists holding exactly their elements
+        impl<$($name: Decodable),+> Decodable for ($($name,)+) {
+            fn decode(buf: &mut &[u8]) -> Result<Self, Error> {
+                let (mut payload, rest) = split_list(buf)?;
+                let value = ($($name::decode(&mut payload)?,)+);
+                if !payload.is_empty() {
+                    return Err(Error::TrailingItems($len));
+                }
+                *buf = rest;
+                Ok(value)
+            }
+        }
+    };
+}
+
+decodable_tuple!(1; A);
+decodable_tuple!(2; A, B);
+decodable_tuple!(3; A, B, C);
+decodable_tuple!(4; A, B, C, D);
+decodable_tuple!(5; A, B, C, D, E);
+decodable_tuple!(6; A, B, C, D, E, F);
+
+/// Splits the next item off `buf`, header included
+pub(crate) fn next_raw<'a>(buf: &mut &'a [u8]) -> Result<&'a [u8], Error> {
+    let (_, _, rest) = split(buf)?;
+    let raw = &buf[..buf.len() - rest.len()];
+    *buf = rest;
+    Ok(raw)
+}
+
+/// Error for an enum variant index the type does not have, used by the derived impls
+#[doc(hidden)]
+pub fn unknown_variant(index: u32) -> Error {
+    Error::Message(format!("unknown variant index {}", index))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encode::encode;
+
+    #[test]
+    fn decode_test() {
+        assert_eq!(decode_exact::<u64>(&encode(&1024u64)).unwrap(), 1024);
+        assert_eq!(decode_exact::<i8>(&encode(&-5i8)).unwrap(), -5);
+        assert!(matches!(
+            decode_exact::<u8>(&encode(&1024u64)),
+            Err(Error::IntegerOverflow)
+        ));
+
+        let nested = vec![
+            (String::from("cat"), vec![Some(1u64), None]),
+            (String::new(), vec![]),
+        ];
+        assert_eq!(
+            decode_exact::<Vec<(String, Vec<Option<u64>>)>>(&encode(&nested)).unwrap(),
+            nested
+        );
+
+        let hash = [0xab; 32];
+        assert_eq!(decode_exact::<[u8; 32]>(&encode(&hash)).unwrap(), hash);
+        assert!(decode_exact::<[u8; 31]>(&encode(&hash)).is_err());
+
+        // Items are consumed one at a time from the front
+        let mut buf = &[0x01, 0x83, b'd', b'o', b'g'][..];
+        assert!(bool::decode(&mut buf).unwrap());
+        assert_eq!(String::decode(&mut buf).unwrap(), "dog");
+        assert!(buf.is_empty());
+    }
+}
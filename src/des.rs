@@ -1,117 +1,505 @@
-use std::char;
+#[cfg(feature = "std")]
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+use core::mem;
+#[cfg(feature = "std")]
+use std::io::{self, Read};
 
+use serde::de;
+use serde::de::value::BorrowedBytesDeserializer;
+use serde::de::IntoDeserializer;
 use serde::de::SeqAccess;
 use serde::de::Visitor;
-use serde::Deserializer;
-use serde::{de, Deserialize};
 
-use crate::error::Error;
+use crate::error::{Error, Limit, Segment};
+use crate::header::Header;
+use crate::macros::trace_item;
+use crate::{encoded, raw, sealed, tail, trailing};
+
+/// Bounds applied while decoding, so hostile input fails with
+/// `Error::LimitExceeded` instead of exhausting the stack or memory
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    /// Maximum nesting depth of lists
+    pub max_depth: usize,
+    /// Maximum payload length of a single string or list
+    pub max_item_len: usize,
+    /// Maximum number of list elements across the whole input
+    pub max_list_elements: usize,
+    /// Upper bound on the element count reported through `size_hint`
+    pub max_preallocation: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            max_depth: 128,
+            max_item_len: 64 * 1024 * 1024,
+            max_list_elements: 16 * 1024 * 1024,
+            max_preallocation: 4096,
+        }
+    }
+}
 
 pub struct RlpDeserializer<'de> {
     pub input: &'de [u8],
+    limits: Limits,
+    depth: usize,
+    elements: usize,
+    trailing: bool,
+    tail: bool,
+    /// Input the deserializer was created with, trace events report offsets into it
+    origin: &'de [u8],
 }
 
 pub fn from_rlp_bytes<'de, T>(v: &'de [u8]) -> Result<T, Error>
 where
     T: de::Deserialize<'de>,
 {
-    let mut deserializer = RlpDeserializer { input: v };
-    let value = de::Deserialize::deserialize(&mut deserializer);
+    from_rlp_bytes_with_limits(v, Limits::default())
+}
+
+pub fn from_rlp_bytes_with_limits<'de, T>(v: &'de [u8], limits: Limits) -> Result<T, Error>
+where
+    T: de::Deserialize<'de>,
+{
+    let mut deserializer = RlpDeserializer::with_limits(v, limits);
+    de::Deserialize::deserialize(&mut deserializer)
+}
+
+/// Decodes one item read from `reader`
+///
+/// Only the bytes of that item are read, the header is parsed first and the payload is
+/// pulled once its length has been checked against `Limits::max_item_len`.
+#[cfg(feature = "std")]
+pub fn from_reader<R, T>(reader: R) -> Result<T, Error>
+where
+    R: io::Read,
+    T: de::DeserializeOwned,
+{
+    from_reader_with_limits(reader, Limits::default())
+}
+
+#[cfg(feature = "std")]
+pub fn from_reader_with_limits<R, T>(mut reader: R, limits: Limits) -> Result<T, Error>
+where
+    R: io::Read,
+    T: de::DeserializeOwned,
+{
+    let mut buf = Vec::new();
+    if !read_item(&mut reader, &limits, &mut buf)? {
+        return Err(Error::Eof);
+    }
+    from_rlp_bytes_with_limits(&buf, limits)
+}
+
+/// Appends the next complete item, header and payload, from `reader` to `buf`
+/// Returns false if the reader was already at its end
+#[cfg(feature = "std")]
+pub(crate) fn read_item<R: io::Read>(
+    reader: &mut R,
+    limits: &Limits,
+    buf: &mut Vec<u8>,
+) -> Result<bool, Error> {
+    let mut prefix = [0u8; 1];
+    loop {
+        match reader.read(&mut prefix) {
+            Ok(0) => return Ok(false),
+            Ok(_) => break,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(Error::Io(err)),
+        }
+    }
+
+    // Long strings and lists carry their length in up to 8 more bytes
+    let mut header = [0u8; 9];
+    header[0] = prefix[0];
+    let len_of_len = match prefix[0] {
+        0xb8..=0xbf => (prefix[0] - 0xb7) as usize,
+        0xf8..=0xff => (prefix[0] - 0xf7) as usize,
+        _ => 0,
+    };
+    let header = &mut header[..1 + len_of_len];
+    reader.read_exact(&mut header[1..]).map_err(eof_or_io)?;
+    buf.extend_from_slice(header);
+
+    let mut rest = &header[..];
+    let payload_len = Header::decode(&mut rest)?.payload_len;
+    if payload_len > limits.max_item_len {
+        return Err(Error::LimitExceeded(Limit::ItemLength));
+    }
+    // A single byte item was complete with its prefix
+    let payload_len = payload_len - rest.len();
+
+    // Grows with the bytes actually read rather than trusting the claimed length up front
+    let read = reader
+        .take(payload_len as u64)
+        .read_to_end(buf)
+        .map_err(Error::Io)?;
+    if read < payload_len {
+        return Err(Error::Eof);
+    }
+    Ok(true)
+}
+
+/// Iterator decoding consecutive top-level items, as found in chain exports and wire captures
+///
+/// Built with `from_slice` or `from_reader`. Iteration ends cleanly at the end of the input,
+/// a truncated item is reported as `Error::Eof` and no further items are decoded after an error.
+pub struct StreamDeserializer<'de, R, T> {
+    source: R,
+    limits: Limits,
+    offset: usize,
+    item_offset: usize,
+    failed: bool,
+    /// Holds the item read from an `io::Read`
+    #[cfg(feature = "std")]
+    buf: Vec<u8>,
+    lifetime: PhantomData<&'de ()>,
+    output: PhantomData<fn() -> T>,
+}
+
+/// Source of a `StreamDeserializer` reading from an `io::Read`
+#[cfg(feature = "std")]
+pub struct IoRead<R>(R);
+
+impl<'de, R, T> StreamDeserializer<'de, R, T> {
+    fn with_source(source: R, limits: Limits) -> Self {
+        StreamDeserializer {
+            source,
+            limits,
+            offset: 0,
+            item_offset: 0,
+            failed: false,
+            #[cfg(feature = "std")]
+            buf: Vec::new(),
+            lifetime: PhantomData,
+            output: PhantomData,
+        }
+    }
+
+    /// Number of bytes consumed so far
+    pub fn byte_offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Offset of the first byte of the item returned last
+    pub fn item_offset(&self) -> usize {
+        self.item_offset
+    }
+
+    fn advance<V>(&mut self, len: usize, value: Result<V, Error>) -> Option<Result<V, Error>> {
+        self.failed = value.is_err();
+        self.item_offset = self.offset;
+        self.offset += len;
+        Some(value)
+    }
+}
+
+impl<'de, T> StreamDeserializer<'de, &'de [u8], T>
+where
+    T: de::Deserialize<'de>,
+{
+    pub fn from_slice(input: &'de [u8]) -> Self {
+        Self::with_source(input, Limits::default())
+    }
+
+    pub fn from_slice_with_limits(input: &'de [u8], limits: Limits) -> Self {
+        Self::with_source(input, limits)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R, T> StreamDeserializer<'static, IoRead<R>, T>
+where
+    R: io::Read,
+    T: de::DeserializeOwned,
+{
+    pub fn from_reader(reader: R) -> Self {
+        Self::with_source(IoRead(reader), Limits::default())
+    }
+
+    pub fn from_reader_with_limits(reader: R, limits: Limits) -> Self {
+        Self::with_source(IoRead(reader), limits)
+    }
+}
+
+impl<'de, T> Iterator for StreamDeserializer<'de, &'de [u8], T>
+where
+    T: de::Deserialize<'de>,
+{
+    type Item = Result<T, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed || self.source.is_empty() {
+            return None;
+        }
+
+        let mut deserializer = RlpDeserializer::with_limits(self.source, self.limits);
+        let item = match deserializer.next_raw_item() {
+            Ok(item) => item,
+            Err(err) => return self.advance(0, Err(err)),
+        };
+        self.source = deserializer.input;
+        self.advance(item.len(), from_rlp_bytes_with_limits(item, self.limits))
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R, T> Iterator for StreamDeserializer<'static, IoRead<R>, T>
+where
+    R: io::Read,
+    T: de::DeserializeOwned,
+{
+    type Item = Result<T, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+
+        self.buf.clear();
+        match read_item(&mut self.source.0, &self.limits, &mut self.buf) {
+            Ok(true) => {}
+            Ok(false) => return None,
+            Err(err) => return self.advance(self.buf.len(), Err(err)),
+        }
+        let value = from_rlp_bytes_with_limits(&self.buf, self.limits);
+        self.advance(self.buf.len(), value)
+    }
+}
 
-    value
+#[cfg(feature = "std")]
+fn eof_or_io(err: io::Error) -> Error {
+    match err.kind() {
+        io::ErrorKind::UnexpectedEof => Error::Eof,
+        _ => Error::Io(err),
+    }
 }
 
 macro_rules! deserialize_int {
-    ($method:ident, $visit:ident, $inttype:ty,$bits:expr) => {
+    ($method:ident, $visit:ident, $inttype:ty, $wide:ty) => {
         fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
-            let byte = self.next_byte().unwrap();
-            match byte {
-                0x80 => {
-                    return visitor.$visit(0);
-                }
-                1..=127 => {
-                    //Single byte
-                    return visitor.$visit(byte as $inttype);
-                }
-                _ => {
-                    let byte_len = byte - 0x80u8;
-
-                    let num_bytes: [u8; $bits] =
-                        self.input[0..byte_len as usize].try_into().unwrap(); // Number in Bytes
-                    let num = <$inttype>::from_be_bytes(num_bytes);
-                    return visitor.$visit(num);
-                }
-            }
+            let num = self.read_u64()?;
+            // Signed values are encoded from their two's complement `u64` form
+            let num = <$inttype>::try_from(num as $wide).map_err(|_| Error::IntegerOverflow)?;
+            visitor.$visit(num)
         }
     };
 }
 
 impl<'de> RlpDeserializer<'de> {
+    pub fn new(input: &'de [u8]) -> Self {
+        Self::with_limits(input, Limits::default())
+    }
+
+    pub fn with_limits(input: &'de [u8], limits: Limits) -> Self {
+        RlpDeserializer {
+            input,
+            limits,
+            depth: 0,
+            elements: 0,
+            trailing: false,
+            tail: false,
+            origin: input,
+        }
+    }
+
     pub fn next_byte(&mut self) -> Result<u8, Error> {
-        let byte = self.input[0];
-        self.input = &self.input[1..];
+        let (&byte, rest) = self.input.split_first().ok_or(Error::Eof)?;
+        self.input = rest;
 
         Ok(byte)
     }
 
-    pub fn read_bytes(&mut self, count: usize) -> &[u8] {
-        let bytes = &self.input[0..count];
-        self.input = &self.input[count..];
+    pub fn read_bytes(&mut self, count: usize) -> Result<&'de [u8], Error> {
+        if count > self.input.len() {
+            return Err(Error::Eof);
+        }
+        let (bytes, rest) = self.input.split_at(count);
+        self.input = rest;
 
-        bytes
+        Ok(bytes)
     }
-}
 
-impl<'a, 'de> de::Deserializer<'de> for &'a mut RlpDeserializer<'de> {
+    /// Splits the next item off the input
+    /// Returns whether the item is a list along with its payload
+    fn next_item(&mut self) -> Result<(bool, &'de [u8]), Error> {
+        let (is_list, header_len, payload_len) = self.peek_header()?;
+        trace_item!(
+            "decode",
+            if is_list { "list" } else { "string" },
+            self.offset(),
+            payload_len,
+            self.depth
+        );
+        let item = self.read_bytes(header_len + payload_len)?;
+        Ok((is_list, &item[header_len..]))
+    }
+
+    /// Position of the input in the slice the deserializer was created with
+    fn offset(&self) -> usize {
+        (self.input.as_ptr() as usize).wrapping_sub(self.origin.as_ptr() as usize)
+    }
+
+    /// Splits the next item off the input, header included
+    pub(crate) fn next_raw_item(&mut self) -> Result<&'de [u8], Error> {
+        let (_, header_len, payload_len) = self.peek_header()?;
+        self.read_bytes(header_len + payload_len)
+    }
+
+    /// Parses the header at the front of the input
+    /// Returns whether the item is a list, the header length and the payload length
+    fn peek_header(&self) -> Result<(bool, usize, usize), Error> {
+        let mut rest = self.input;
+        let Header { list, payload_len } = Header::decode(&mut rest)?;
+        let header_len = self.input.len() - rest.len();
+
+        if payload_len > self.limits.max_item_len {
+            return Err(Error::LimitExceeded(Limit::ItemLength));
+        }
+        // Checked against the remaining input before slicing, a claimed length is never trusted
+        if payload_len > self.input.len() - header_len {
+            return Err(Error::Eof);
+        }
+        Ok((list, header_len, payload_len))
+    }
+
+    fn next_string(&mut self) -> Result<&'de [u8], Error> {
+        match self.next_item()? {
+            (false, payload) => Ok(payload),
+            (true, _) => Err(Error::ExpectedString),
+        }
+    }
+
+    /// Visits the elements of the next list
+    /// `fields` names the elements of a struct so errors can report the failing field
+    fn deserialize_list<V>(
+        &mut self,
+        fields: Option<&'static [&'static str]>,
+        visitor: V,
+    ) -> Result<V::Value, Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        if mem::take(&mut self.tail) {
+            return visitor.visit_seq(&mut ListAccess {
+                de: &mut *self,
+                fields: None,
+                index: 0,
+            });
+        }
+
+        self.with_list(|de| {
+            let mut access = ListAccess {
+                de,
+                fields,
+                index: 0,
+            };
+            let value = visitor.visit_seq(&mut access);
+            (value, access.index)
+        })
+    }
+
+    /// Runs `visit` with the payload of the next list as input
+    /// `visit` returns the number of items it consumed along with its result
+    fn with_list<T>(
+        &mut self,
+        visit: impl FnOnce(&mut Self) -> (Result<T, Error>, usize),
+    ) -> Result<T, Error> {
+        let payload = match self.next_item()? {
+            (true, payload) => payload,
+            (false, _) => return Err(Error::ExpectedList),
+        };
+        if self.depth >= self.limits.max_depth {
+            return Err(Error::LimitExceeded(Limit::Depth));
+        }
+        let allow_trailing = mem::take(&mut self.trailing);
+
+        // Elements are read from the list payload, the rest of the input is restored afterwards
+        let rest = mem::replace(&mut self.input, payload);
+        self.depth += 1;
+        let (value, consumed) = visit(self);
+        self.depth -= 1;
+        let remaining = mem::replace(&mut self.input, rest);
+
+        // The visitor has to consume the whole list unless the type opted into trailing items
+        if value.is_ok() && !remaining.is_empty() && !allow_trailing {
+            return Err(Error::TrailingItems(consumed));
+        }
+        value
+    }
+
+    /// Visits the contents of the next byte string as one item, with the limits carried over
+    fn deserialize_encoded<V>(&mut self, visitor: V) -> Result<V::Value, Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        let payload = self.next_string()?;
+        if self.depth >= self.limits.max_depth {
+            return Err(Error::LimitExceeded(Limit::Depth));
+        }
+        let mut scoped = RlpDeserializer {
+            input: payload,
+            limits: self.limits,
+            depth: self.depth + 1,
+            elements: self.elements,
+            trailing: false,
+            tail: false,
+            origin: self.origin,
+        };
+        let value = visitor.visit_newtype_struct(&mut scoped)?;
+        self.elements = scoped.elements;
+        if !scoped.input.is_empty() {
+            return Err(Error::TrailingItems(1));
+        }
+        Ok(value)
+    }
+
+    fn read_u64(&mut self) -> Result<u64, Error> {
+        let payload = self.next_string()?;
+        if payload.len() > 8 {
+            return Err(Error::IntegerOverflow);
+        }
+        if payload.first() == Some(&0) {
+            return Err(Error::NonCanonical("integer with leading zero bytes"));
+        }
+        Ok(payload.iter().fold(0u64, |acc, &b| (acc << 8) | b as u64))
+    }
+}
+impl<'de> de::Deserializer<'de> for &mut RlpDeserializer<'de> {
     type Error = Error;
 
     fn deserialize_bool<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: de::Visitor<'de>,
     {
-        let byte = self.next_byte().unwrap();
-        if byte == 0x01 {
-            return visitor.visit_bool(true);
-        } else if byte == 0x80 {
-            return visitor.visit_bool(false);
-        } else {
-            println!("Not supppp");
-            return Err(Error);
+        match self.next_string()? {
+            [0x01] => visitor.visit_bool(true),
+            [] => visitor.visit_bool(false),
+            _ => Err(Error::Message("invalid boolean".into())),
         }
     }
 
+    /// RLP only knows byte strings and lists, so that is all the visitor gets
     fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: de::Visitor<'de>,
     {
-        println!("any is Not Supported");
-        unimplemented!()
+        let (is_list, _, _) = self.peek_header()?;
+        if is_list {
+            self.deserialize_list(None, visitor)
+        } else {
+            self.deserialize_bytes(visitor)
+        }
     }
 
     fn deserialize_bytes<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: de::Visitor<'de>,
     {
-        let next_byte = self.next_byte().unwrap();
-
-        if next_byte < 0x7f {
-            visitor.visit_bytes(&[next_byte])
-        } else if next_byte < 0x80u8 + 55 {
-            let length_of_arr = next_byte - 0x80u8;
-            let bytes = self.read_bytes(length_of_arr as usize);
-            visitor.visit_bytes(bytes)
-        } else if next_byte < 0xbf {
-            let length_of_array_length_bytes = next_byte - 0xB7;
-            let array_length_bytes: [u8; 8] = self
-                .read_bytes(length_of_array_length_bytes as usize)
-                .try_into()
-                .unwrap();
-            let array_length = usize::from_be_bytes(array_length_bytes);
-            let data_bytes = self.read_bytes(array_length);
-            visitor.visit_bytes(data_bytes)
-        } else {
-            self.deserialize_bytes(visitor)
-        }
+        let bytes = self.next_string()?;
+        visitor.visit_borrowed_bytes(bytes)
     }
 
     fn deserialize_byte_buf<V>(self, visitor: V) -> Result<V::Value, Self::Error>
@@ -121,86 +509,85 @@ impl<'a, 'de> de::Deserializer<'de> for &'a mut RlpDeserializer<'de> {
         self.deserialize_bytes(visitor)
     }
 
-    /// Char is Always 4 byte
-    /// Deserialise_byte cannot be used here because of that reason
+    /// Char is encoded as its UTF-8 bytes
     fn deserialize_char<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: de::Visitor<'de>,
     {
-        let next_byte = self.next_byte().unwrap();
-        if next_byte < 0x7f {
-            //Since char is always 4 byte
-            let bytes = [next_byte, 0, 0, 0];
-            let num = u32::from_le_bytes(bytes);
-            let actual_char = char::from_u32(num).unwrap();
-            visitor.visit_char(actual_char)
-        } else {
-            let length_of_arr = next_byte - 0x80u8;
-            let bytes: [u8; 4] = self.read_bytes(length_of_arr as usize).try_into().unwrap();
-            let num = u32::from_le_bytes(bytes);
-            let actual_char = char::from_u32(num).unwrap();
-            visitor.visit_char(actual_char)
+        let bytes = self.next_string()?;
+        let mut chars = core::str::from_utf8(bytes)
+            .map_err(|_| Error::Message("invalid char".into()))?
+            .chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => visitor.visit_char(c),
+            _ => Err(Error::Message("invalid char".into())),
         }
     }
 
+    /// Unit variants are their index, other variants a list of the index and the fields
     fn deserialize_enum<V>(
         self,
-        name: &'static str,
-        variants: &'static [&'static str],
+        _name: &'static str,
+        _variants: &'static [&'static str],
         visitor: V,
     ) -> Result<V::Value, Self::Error>
     where
         V: de::Visitor<'de>,
     {
-        println!("Enum is Not Supported");
-        unimplemented!()
+        let (is_list, _, _) = self.peek_header()?;
+        if !is_list {
+            return visitor.visit_enum(UnitVariant(self));
+        }
+        self.with_list(|de| {
+            let mut access = VariantAccess { de, consumed: 0 };
+            let value = visitor.visit_enum(&mut access);
+            (value, access.consumed)
+        })
     }
 
-    fn deserialize_f32<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    fn deserialize_f32<V>(self, _visitor: V) -> Result<V::Value, Self::Error>
     where
         V: de::Visitor<'de>,
     {
-        unimplemented!()
+        Err(Error::Unsupported("f32"))
     }
 
-    fn deserialize_f64<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    fn deserialize_f64<V>(self, _visitor: V) -> Result<V::Value, Self::Error>
     where
         V: de::Visitor<'de>,
     {
-        unimplemented!()
+        Err(Error::Unsupported("f64"))
     }
 
-    fn deserialize_i128<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    fn deserialize_i128<V>(self, _visitor: V) -> Result<V::Value, Self::Error>
     where
         V: de::Visitor<'de>,
     {
-        unimplemented!()
+        Err(Error::Unsupported("i128"))
     }
 
-    fn deserialize_identifier<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    fn deserialize_identifier<V>(self, _visitor: V) -> Result<V::Value, Self::Error>
     where
         V: de::Visitor<'de>,
     {
-        println!("identifier is Not Supported");
-        unimplemented!()
+        Err(Error::Unsupported("identifier"))
     }
 
-    fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    fn deserialize_ignored_any<V>(self, _visitor: V) -> Result<V::Value, Self::Error>
     where
         V: de::Visitor<'de>,
     {
-        println!("ignore Any is Not Supported");
-        unimplemented!()
+        Err(Error::Unsupported("ignored any"))
     }
 
-    fn deserialize_map<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    fn deserialize_map<V>(self, _visitor: V) -> Result<V::Value, Self::Error>
     where
         V: de::Visitor<'de>,
     {
-        println!("Map is Not Supported");
-        unimplemented!()
+        Err(Error::Unsupported("map"))
     }
 
+    /// Newtypes are transparent, the wrapped value is decoded in place
     fn deserialize_newtype_struct<V>(
         self,
         name: &'static str,
@@ -209,116 +596,192 @@ impl<'a, 'de> de::Deserializer<'de> for &'a mut RlpDeserializer<'de> {
     where
         V: de::Visitor<'de>,
     {
-        println!("NewType Struct is Not Supported");
-        unimplemented!()
+        if name == trailing::NAME {
+            // Only applies to the list that starts the wrapped value
+            self.trailing = true;
+            let value = visitor.visit_newtype_struct(&mut *self);
+            self.trailing = false;
+            return value;
+        }
+        if name == raw::NAME {
+            // Handed the whole item, header included
+            return visitor.visit_borrowed_bytes(self.next_raw_item()?);
+        }
+        if name == encoded::NAME {
+            return self.deserialize_encoded(visitor);
+        }
+        if name == sealed::NAME {
+            return visitor.visit_seq(SealedAccess {
+                start: self.input,
+                de: self,
+                index: 0,
+            });
+        }
+        if name == tail::NAME {
+            // The sequence inside is read from the rest of the enclosing list
+            self.tail = true;
+            let value = visitor.visit_newtype_struct(&mut *self);
+            self.tail = false;
+            return value;
+        }
+        visitor.visit_newtype_struct(self)
     }
 
+    /// Optional struct fields missing from the end of the list are `None`
+    /// Elsewhere `None` is encoded as an empty list, same as the serializer
     fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: de::Visitor<'de>,
     {
-        println!("Option is Not Supported");
-        unimplemented!()
+        match self.input.first() {
+            None => visitor.visit_none(),
+            Some(0xc0) => {
+                self.next_byte()?;
+                visitor.visit_none()
+            }
+            Some(_) => visitor.visit_some(self),
+        }
     }
 
+    /// Strings are their UTF-8 bytes, borrowed from the input
     fn deserialize_str<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: de::Visitor<'de>,
     {
-        println!("Str is Not Supported");
-        unimplemented!()
+        let bytes = self.next_string()?;
+        let s = core::str::from_utf8(bytes).map_err(|_| Error::Message("invalid string".into()))?;
+        visitor.visit_borrowed_str(s)
     }
 
     fn deserialize_string<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: de::Visitor<'de>,
     {
-        println!("More");
         self.deserialize_bytes(visitor)
     }
 
     fn deserialize_struct<V>(
         self,
-        name: &'static str,
+        _name: &'static str,
         fields: &'static [&'static str],
         visitor: V,
     ) -> Result<V::Value, Self::Error>
     where
         V: de::Visitor<'de>,
     {
-        self.deserialize_seq(visitor)
+        self.deserialize_list(Some(fields), visitor)
     }
 
-    fn deserialize_u128<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    fn deserialize_u128<V>(self, _visitor: V) -> Result<V::Value, Self::Error>
     where
         V: de::Visitor<'de>,
     {
-        unimplemented!()
+        Err(Error::Unsupported("u128"))
     }
 
-    deserialize_int!(deserialize_u64, visit_u64, u64, 8);
-    deserialize_int!(deserialize_u32, visit_u32, u32, 4);
-    deserialize_int!(deserialize_u16, visit_u16, u16, 2);
-    deserialize_int!(deserialize_u8, visit_u8, u8, 1);
+    deserialize_int!(deserialize_u64, visit_u64, u64, u64);
+    deserialize_int!(deserialize_u32, visit_u32, u32, u64);
+    deserialize_int!(deserialize_u16, visit_u16, u16, u64);
+    deserialize_int!(deserialize_u8, visit_u8, u8, u64);
 
-    deserialize_int!(deserialize_i64, visit_i64, i64, 8);
-    deserialize_int!(deserialize_i32, visit_i32, i32, 4);
-    deserialize_int!(deserialize_i16, visit_i16, i16, 2);
-    deserialize_int!(deserialize_i8, visit_i8, i8, 1);
+    deserialize_int!(deserialize_i64, visit_i64, i64, i64);
+    deserialize_int!(deserialize_i32, visit_i32, i32, i64);
+    deserialize_int!(deserialize_i16, visit_i16, i16, i64);
+    deserialize_int!(deserialize_i8, visit_i8, i8, i64);
 
     fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: de::Visitor<'de>,
     {
-        visitor.visit_seq(self)
+        self.deserialize_list(None, visitor)
     }
 
-    fn deserialize_tuple<V>(self, len: usize, visitor: V) -> Result<V::Value, Self::Error>
+    fn deserialize_tuple<V>(self, _len: usize, _visitor: V) -> Result<V::Value, Self::Error>
     where
         V: de::Visitor<'de>,
     {
-        println!("Tuple is Not Supported");
-        Err(Error) //Not Supported
+        Err(Error::Unsupported("tuple"))
     }
 
     fn deserialize_tuple_struct<V>(
         self,
-        name: &'static str,
-        len: usize,
-        visitor: V,
+        _name: &'static str,
+        _len: usize,
+        _visitor: V,
     ) -> Result<V::Value, Self::Error>
     where
         V: de::Visitor<'de>,
     {
-        println!("Tuple Struct is Not Supported");
         // Not Supported
-        Err(Error)
+        Err(Error::Unsupported("tuple struct"))
     }
 
     ///TODO:
-    fn deserialize_unit<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    fn deserialize_unit<V>(self, _visitor: V) -> Result<V::Value, Self::Error>
     where
         V: de::Visitor<'de>,
     {
-        println!("Tuple is Not Supported");
-        return Err(Error);
+        Err(Error::Unsupported("unit"))
     }
 
     fn deserialize_unit_struct<V>(
         self,
-        name: &'static str,
-        visitor: V,
+        _name: &'static str,
+        _visitor: V,
     ) -> Result<V::Value, Self::Error>
     where
         V: de::Visitor<'de>,
     {
-        println!("Unit Struct is Not Supported");
         // Not Supported
-        Err(Error)
+        Err(Error::Unsupported("unit struct"))
+    }
+}
+
+/// Hands a `Sealed` the decoded value, then the span of input it was decoded from
+struct SealedAccess<'a, 'de> {
+    de: &'a mut RlpDeserializer<'de>,
+    /// Input before the value was decoded
+    start: &'de [u8],
+    index: usize,
+}
+
+impl<'de> SeqAccess<'de> for SealedAccess<'_, 'de> {
+    type Error = Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, Self::Error>
+    where
+        T: de::DeserializeSeed<'de>,
+    {
+        self.index += 1;
+        match self.index {
+            1 => seed.deserialize(&mut *self.de).map(Some),
+            2 => {
+                let span = &self.start[..self.start.len() - self.de.input.len()];
+                seed.deserialize(BorrowedBytesDeserializer::new(span))
+                    .map(Some)
+            }
+            _ => Ok(None),
+        }
     }
 }
 
-impl<'a, 'de> SeqAccess<'de> for &'a mut RlpDeserializer<'de> {
+/// Gives access to the elements of a list while its payload is the deserializer input
+struct ListAccess<'a, 'de> {
+    de: &'a mut RlpDeserializer<'de>,
+    fields: Option<&'static [&'static str]>,
+    index: usize,
+}
+
+impl ListAccess<'_, '_> {
+    fn segment(&self) -> Segment {
+        match self.fields.and_then(|fields| fields.get(self.index)) {
+            Some(field) => Segment::Field(field),
+            None => Segment::Index(self.index),
+        }
+    }
+}
+
+impl<'de> SeqAccess<'de> for &mut ListAccess<'_, 'de> {
     type Error = Error;
 
     fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, Self::Error>
@@ -326,27 +789,437 @@ impl<'a, 'de> SeqAccess<'de> for &'a mut RlpDeserializer<'de> {
         T: de::DeserializeSeed<'de>,
     {
         //Don't deserialise if input becomes empty
-        if self.input.is_empty() {
-            return Ok(None);
+        //Struct fields past the end are still offered to the type so optional ones decode as None
+        if self.de.input.is_empty() {
+            return match self.fields {
+                Some(fields) if self.index < fields.len() => {
+                    match seed.deserialize(&mut *self.de) {
+                        Ok(value) => {
+                            self.index += 1;
+                            Ok(Some(value))
+                        }
+                        // Field is not optional, serde falls back to its default or reports it missing
+                        Err(Error::Eof) => Ok(None),
+                        Err(err) => Err(err.within(self.segment())),
+                    }
+                }
+                _ => Ok(None),
+            };
+        }
+        if self.de.elements >= self.de.limits.max_list_elements {
+            return Err(Error::LimitExceeded(Limit::ListElements));
         }
-        seed.deserialize(&mut **self).map(Some)
+        self.de.elements += 1;
+        let value = seed
+            .deserialize(&mut *self.de)
+            .map_err(|err| err.within(self.segment()))?;
+        self.index += 1;
+        Ok(Some(value))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        // Every element takes at least one byte, so the payload length bounds the count
+        Some(self.de.input.len().min(self.de.limits.max_preallocation))
+    }
+}
+
+/// Variant encoded as its bare index
+struct UnitVariant<'a, 'de>(&'a mut RlpDeserializer<'de>);
+
+impl<'de> de::EnumAccess<'de> for UnitVariant<'_, 'de> {
+    type Error = Error;
+    type Variant = Self;
+
+    fn variant_seed<T>(self, seed: T) -> Result<(T::Value, Self), Self::Error>
+    where
+        T: de::DeserializeSeed<'de>,
+    {
+        let index = u32::try_from(self.0.read_u64()?).map_err(|_| Error::IntegerOverflow)?;
+        let value = seed.deserialize(index.into_deserializer())?;
+        Ok((value, self))
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for UnitVariant<'_, 'de> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T>(self, _seed: T) -> Result<T::Value, Self::Error>
+    where
+        T: de::DeserializeSeed<'de>,
+    {
+        Err(Error::ExpectedList)
+    }
+
+    fn tuple_variant<V>(self, _len: usize, _visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        Err(Error::ExpectedList)
+    }
+
+    fn struct_variant<V>(
+        self,
+        _fields: &'static [&'static str],
+        _visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        Err(Error::ExpectedList)
+    }
+}
+
+/// Variant encoded as a list, while its payload is the deserializer input
+struct VariantAccess<'a, 'de> {
+    de: &'a mut RlpDeserializer<'de>,
+    /// Items read from the list, the index included
+    consumed: usize,
+}
+
+impl<'de> de::EnumAccess<'de> for &mut VariantAccess<'_, 'de> {
+    type Error = Error;
+    type Variant = Self;
+
+    fn variant_seed<T>(self, seed: T) -> Result<(T::Value, Self), Self::Error>
+    where
+        T: de::DeserializeSeed<'de>,
+    {
+        let index = u32::try_from(self.de.read_u64()?).map_err(|_| Error::IntegerOverflow)?;
+        let value = seed.deserialize(index.into_deserializer())?;
+        self.consumed = 1;
+        Ok((value, self))
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for &mut VariantAccess<'_, 'de> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value, Self::Error>
+    where
+        T: de::DeserializeSeed<'de>,
+    {
+        let value = seed
+            .deserialize(&mut *self.de)
+            .map_err(|err| err.within(Segment::Index(0)))?;
+        self.consumed += 1;
+        Ok(value)
+    }
+
+    fn tuple_variant<V>(self, _len: usize, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.fields(None, visitor)
+    }
+
+    fn struct_variant<V>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.fields(Some(fields), visitor)
+    }
+}
+
+impl<'de> VariantAccess<'_, 'de> {
+    /// Visits the items after the index as the variant fields
+    fn fields<V>(
+        &mut self,
+        fields: Option<&'static [&'static str]>,
+        visitor: V,
+    ) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        let mut access = ListAccess {
+            de: &mut *self.de,
+            fields,
+            index: 0,
+        };
+        let value = visitor.visit_seq(&mut access);
+        self.consumed += access.index;
+        value
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use serde::Serialize;
+    use crate::tail::Tail;
+    use crate::trailing::Trailing;
+    use serde::{Deserialize, Serialize};
 
-    #[derive(Serialize, Deserialize, Debug)]
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
     struct Point {
         y: Vec<String>,
     }
 
     #[test]
     fn des_test() {
-        let bytes = from_rlp_bytes::<Point>(&[200, 131, 99, 97, 116, 131, 100, 111, 103]);
+        let point = from_rlp_bytes::<Point>(&[201, 200, 131, 99, 97, 116, 131, 100, 111, 103]);
+
+        assert_eq!(
+            point.unwrap(),
+            Point {
+                y: vec![String::from("cat"), String::from("dog")]
+            }
+        );
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Name<'a> {
+        name: &'a str,
+    }
+
+    #[test]
+    fn borrowed_str_test() {
+        let bytes = [0xc4, 0x83, b'c', b'a', b't'];
+        assert_eq!(
+            from_rlp_bytes::<Name>(&bytes).unwrap(),
+            Name { name: "cat" }
+        );
+        assert!(from_rlp_bytes::<Name>(&[0xc2, 0x81, 0xff]).is_err());
+
+        // Types RLP has no encoding for are errors
+        let err = from_rlp_bytes::<std::collections::BTreeMap<u8, u8>>(&[0xc0]).unwrap_err();
+        assert!(matches!(err, Error::Unsupported("map")));
+        assert!(from_rlp_bytes::<f64>(&[0x80]).is_err());
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Payment {
+        a: u64,
+        b: Vec<u64>,
+    }
+
+    #[test]
+    fn round_trip_test() {
+        let payment = Payment {
+            a: 1,
+            b: vec![2, 3],
+        };
+        let bytes = crate::ser::to_rlp_bytes(&payment).unwrap();
+        assert_eq!(bytes, [0xc4, 0x01, 0xc2, 0x02, 0x03]);
+        assert_eq!(from_rlp_bytes::<Payment>(&bytes).unwrap(), payment);
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    enum Command {
+        Ping,
+        Get(u64),
+        Put { key: u64, value: String },
+        Move(u64, u64),
+    }
+
+    #[test]
+    fn enum_test() {
+        let commands = [
+            (Command::Ping, vec![0x80]),
+            (Command::Get(7), vec![0xc2, 0x01, 0x07]),
+            (
+                Command::Put {
+                    key: 1,
+                    value: String::from("cat"),
+                },
+                vec![0xc6, 0x02, 0x01, 0x83, b'c', b'a', b't'],
+            ),
+            (Command::Move(3, 4), vec![0xc3, 0x03, 0x03, 0x04]),
+        ];
+        for (command, bytes) in commands {
+            assert_eq!(crate::ser::to_rlp_bytes(&command).unwrap(), bytes);
+            assert_eq!(from_rlp_bytes::<Command>(&bytes).unwrap(), command);
+        }
+
+        // Variant the enum does not have and a unit variant holding data
+        assert!(from_rlp_bytes::<Command>(&[0x09]).is_err());
+        assert!(from_rlp_bytes::<Command>(&[0xc2, 0x80, 0x01]).is_err());
+    }
+
+    #[test]
+    fn limits_test() {
+        let limits = Limits {
+            max_depth: 2,
+            ..Limits::default()
+        };
+        let nested = from_rlp_bytes_with_limits::<Vec<Vec<Vec<u8>>>>(&[0xc2, 0xc1, 0xc0], limits);
+        assert!(matches!(
+            nested.unwrap_err().inner(),
+            Error::LimitExceeded(Limit::Depth)
+        ));
+
+        // Long string header claiming an 8 byte payload length
+        let huge = [0xbf, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x00];
+        let bytes = from_rlp_bytes::<Vec<u8>>(&huge);
+        assert!(matches!(
+            bytes,
+            Err(Error::LimitExceeded(Limit::ItemLength))
+        ));
+
+        let limits = Limits {
+            max_list_elements: 2,
+            ..Limits::default()
+        };
+        let many = from_rlp_bytes_with_limits::<Vec<u8>>(&[0xc3, 0x01, 0x02, 0x03], limits);
+        assert!(matches!(
+            many,
+            Err(Error::LimitExceeded(Limit::ListElements))
+        ));
+    }
+
+    #[derive(Deserialize, Debug)]
+    struct Pair {
+        _a: u8,
+        _b: u8,
+    }
+
+    #[derive(Deserialize, Debug)]
+    struct Pairs {
+        _pairs: Vec<Pair>,
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Hello {
+        version: u8,
+        name: String,
+    }
+
+    #[test]
+    fn arity_test() {
+        // Hello from a newer peer with an extra capability field
+        let bytes = [0xc7, 0x05, 0x83, 0x67, 0x65, 0x74, 0xc1, 0x01];
+
+        let err = from_rlp_bytes::<Hello>(&bytes).unwrap_err();
+        assert!(matches!(err, Error::TrailingItems(2)));
+
+        let hello = from_rlp_bytes::<Trailing<Hello>>(&bytes).unwrap();
+        assert_eq!(
+            hello.into_inner(),
+            Hello {
+                version: 5,
+                name: String::from("get")
+            }
+        );
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Header {
+        number: u64,
+        base_fee: Option<u64>,
+        withdrawals_root: Option<Vec<u8>>,
+    }
+
+    #[test]
+    fn optional_fields_test() {
+        let legacy = from_rlp_bytes::<Header>(&[0xc1, 0x05]).unwrap();
+        assert_eq!(
+            legacy,
+            Header {
+                number: 5,
+                base_fee: None,
+                withdrawals_root: None
+            }
+        );
+
+        let london = from_rlp_bytes::<Header>(&[0xc4, 0x05, 0x82, 0x03, 0xe8]).unwrap();
+        assert_eq!(london.base_fee, Some(1000));
+        assert_eq!(london.withdrawals_root, None);
+
+        let missing = from_rlp_bytes::<Hello>(&[0xc1, 0x05]).unwrap_err();
+        assert_eq!(
+            missing.to_string(),
+            "invalid length 1, expected struct Hello with 2 elements"
+        );
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Versioned {
+        version: u8,
+        rest: Tail<u64>,
+    }
+
+    #[test]
+    fn tail_test() {
+        let record = from_rlp_bytes::<Versioned>(&[0xc5, 0x01, 0x02, 0x82, 0x04, 0x00]).unwrap();
+        assert_eq!(record.version, 1);
+        assert_eq!(record.rest, Tail(vec![2, 1024]));
+
+        let empty = from_rlp_bytes::<Versioned>(&[0xc1, 0x01]).unwrap();
+        assert!(empty.rest.is_empty());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn from_reader_test() {
+        let bytes = [201, 200, 131, 99, 97, 116, 131, 100, 111, 103, 0xff];
+        let mut reader = &bytes[..];
+        let point = from_reader::<_, Point>(&mut reader).unwrap();
+
+        assert_eq!(point.y, [String::from("cat"), String::from("dog")]);
+        // Only the first item was read
+        assert_eq!(reader, [0xff]);
+
+        let truncated = from_reader::<_, Point>(&bytes[..6]);
+        assert!(matches!(truncated, Err(Error::Eof)));
+
+        let limits = Limits {
+            max_item_len: 1024,
+            ..Limits::default()
+        };
+        let huge = [0xbb, 0x7f, 0xff, 0xff, 0xff];
+        let bytes = from_reader_with_limits::<_, Vec<u8>>(&huge[..], limits);
+        assert!(matches!(
+            bytes,
+            Err(Error::LimitExceeded(Limit::ItemLength))
+        ));
+    }
+
+    #[test]
+    fn stream_test() {
+        // Three items, the last one cut short
+        let bytes = [0x05, 0x82, 0x04, 0x00, 0xc2, 0x01, 0x02, 0x83, 0x63];
+
+        let mut stream = StreamDeserializer::<_, u64>::from_slice(&bytes[..7]);
+        assert_eq!(stream.next().unwrap().unwrap(), 5);
+        assert_eq!(stream.next().unwrap().unwrap(), 1024);
+        assert_eq!(stream.item_offset(), 1);
+        assert!(stream.next().unwrap().is_err());
+        assert!(stream.next().is_none());
+
+        let stream = StreamDeserializer::<_, Vec<u8>>::from_slice(&bytes[4..]);
+        let items: Vec<_> = stream.collect();
+        assert_eq!(items[0].as_ref().unwrap(), &[1, 2]);
+        assert!(matches!(items[1], Err(Error::Eof)));
+
+        #[cfg(feature = "std")]
+        {
+            let mut stream = StreamDeserializer::<_, Vec<u8>>::from_reader(&bytes[4..7]);
+            assert_eq!(stream.next().unwrap().unwrap(), [1, 2]);
+            assert_eq!(stream.byte_offset(), 3);
+            assert!(stream.next().is_none());
+        }
+    }
+
+    #[test]
+    fn error_path_test() {
+        // Second pair holds 256 in a u8 field
+        let bytes = [0xc9, 0xc8, 0xc2, 0x01, 0x02, 0xc4, 0x01, 0x82, 0x01, 0x00];
+        let err = from_rlp_bytes::<Pairs>(&bytes).unwrap_err();
 
-        println!("{:?}", bytes);
+        assert_eq!(
+            err.to_string(),
+            "_pairs[1]._b: integer does not fit in target type"
+        );
+        assert!(matches!(err.inner(), Error::IntegerOverflow));
     }
 }
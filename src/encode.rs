@@ -0,0 +1,330 @@
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::header::Header;
+
+/// Destination of `Encodable::encode`, writing to it cannot fail
+///
+/// With the `bytes` feature every `bytes::BufMut` is one, so values encode straight into a
+/// `BytesMut`.
+pub trait BufMut {
+    fn put_slice(&mut self, src: &[u8]);
+
+    fn put_u8(&mut self, b: u8) {
+        self.put_slice(&[b]);
+    }
+}
+
+#[cfg(not(feature = "bytes"))]
+impl BufMut for Vec<u8> {
+    fn put_slice(&mut self, src: &[u8]) {
+        self.extend_from_slice(src);
+    }
+}
+
+#[cfg(feature = "bytes")]
+impl<B: bytes::BufMut + ?Sized> BufMut for B {
+    fn put_slice(&mut self, src: &[u8]) {
+        bytes::BufMut::put_slice(self, src);
+    }
+}
+
+/// Counts the bytes put into it, backs the default `Encodable::length`
+struct LengthCounter(usize);
+
+impl BufMut for LengthCounter {
+    fn put_slice(&mut self, src: &[u8]) {
+        self.0 += src.len();
+    }
+}
+
+/// Types encoding themselves straight into a buffer, without going through serde
+///
+/// Implementations produce the same bytes as `to_rlp_bytes` does for the type.
+pub trait Encodable {
+    fn encode(&self, out: &mut dyn BufMut);
+
+    /// Encoded length in bytes, header included
+    fn length(&self) -> usize {
+        let mut counter = LengthCounter(0);
+        self.encode(&mut counter);
+        counter.0
+    }
+}
+
+/// Encodes `value` into a new buffer of the exact size
+pub fn encode<T: Encodable + ?Sized>(value: &T) -> Vec<u8> {
+    let mut out = Vec::with_capacity(value.length());
+    value.encode(&mut out);
+    out
+}
+
+/// Encodes the items as one list
+pub fn encode_list<T: Encodable>(items: &[T], out: &mut dyn BufMut) {
+    Header {
+        list: true,
+        payload_len: list_payload_length(items),
+    }
+    .encode(out);
+    for item in items {
+        item.encode(out);
+    }
+}
+
+/// Encoded length of `encode_list`, header included
+pub fn list_length<T: Encodable>(items: &[T]) -> usize {
+    let payload_len = list_payload_length(items);
+    payload_len
+        + Header {
+            list: true,
+            payload_len,
+        }
+        .length()
+}
+
+fn list_payload_length<T: Encodable>(items: &[T]) -> usize {
+    items.iter().map(Encodable::length).sum()
+}
+
+/// Encodes `bytes` as one byte string
+pub fn encode_bytes(bytes: &[u8], out: &mut dyn BufMut) {
+    if bytes.len() == 1 && bytes[0] <= 0x7f {
+        //Single byte
+        out.put_u8(bytes[0]);
+    } else {
+        Header {
+            list: false,
+            payload_len: bytes.len(),
+        }
+        .encode(out);
+        out.put_slice(bytes);
+    }
+}
+
+/// Encoded length of `encode_bytes`, header included
+pub fn bytes_length(bytes: &[u8]) -> usize {
+    if bytes.len() == 1 && bytes[0] <= 0x7f {
+        1
+    } else {
+        bytes.len()
+            + Header {
+                list: false,
+                payload_len: bytes.len(),
+            }
+            .length()
+    }
+}
+
+/// Integers are big endian without leading zero bytes, zero is the empty string
+fn encode_u128(v: u128, out: &mut dyn BufMut) {
+    let len = 16 - v.leading_zeros() as usize / 8;
+    encode_bytes(&v.to_be_bytes()[16 - len..], out);
+}
+
+fn u128_length(v: u128) -> usize {
+    match v {
+        0..=0x7f => 1,
+        _ => 1 + 16 - v.leading_zeros() as usize / 8,
+    }
+}
+
+macro_rules! encodable_int {
+    ($($inttype:ty),*) => {
+        $(
+            impl Encodable for $inttype {
+                fn encode(&self, out: &mut dyn BufMut) {
+                    encode_u128(*self as u128, out)
+                }
+
+                fn length(&self) -> usize {
+                    u128_length(*self as u128)
+                }
+            }
+        )*
+    };
+}
+
+encodable_int!(u8, u16, u32, u64, u128, usize);
+
+// Signed values are encoded from their two's complement `u64` form, as `RlpSerializer` does
+macro_rules! encodable_signed_int {
+    ($($inttype:ty),*) => {
+        $(
+            impl Encodable for $inttype {
+                fn encode(&self, out: &mut dyn BufMut) {
+                    encode_u128(*self as u64 as u128, out)
+                }
+
+                fn length(&self) -> usize {
+                    u128_length(*self as u64 as u128)
+                }
+            }
+        )*
+    };
+}
+
+encodable_signed_int!(i8, i16, i32, i64, isize);
+
+impl Encodable for bool {
+    fn encode(&self, out: &mut dyn BufMut) {
+        out.put_u8(if *self { 0x01 } else { 0x80 });
+    }
+
+    fn length(&self) -> usize {
+        1
+    }
+}
+
+impl Encodable for char {
+    fn encode(&self, out: &mut dyn BufMut) {
+        let mut buff = [0u8; 4];
+        encode_bytes(self.encode_utf8(&mut buff).as_bytes(), out);
+    }
+}
+
+impl Encodable for str {
+    fn encode(&self, out: &mut dyn BufMut) {
+        encode_bytes(self.as_bytes(), out);
+    }
+
+    fn length(&self) -> usize {
+        bytes_length(self.as_bytes())
+    }
+}
+
+impl Encodable for String {
+    fn encode(&self, out: &mut dyn BufMut) {
+        self.as_str().encode(out);
+    }
+
+    fn length(&self) -> usize {
+        self.as_str().length()
+    }
+}
+
+/// Fixed size byte arrays such as hashes are byte strings
+///
+/// Arrays of other element types are deliberately left out, a `[T; N]` list impl would
+/// overlap with this one. Use a `Vec` for fixed size lists instead.
+impl<const N: usize> Encodable for [u8; N] {
+    fn encode(&self, out: &mut dyn BufMut) {
+        encode_bytes(self, out);
+    }
+
+    fn length(&self) -> usize {
+        bytes_length(self)
+    }
+}
+
+impl<T: Encodable> Encodable for Vec<T> {
+    fn encode(&self, out: &mut dyn BufMut) {
+        encode_list(self, out);
+    }
+
+    fn length(&self) -> usize {
+        list_length(self)
+    }
+}
+
+/// `None` is an empty list, like `RlpSerializer` outside of struct fields
+impl<T: Encodable> Encodable for Option<T> {
+    fn encode(&self, out: &mut dyn BufMut) {
+        match self {
+            Some(value) => value.encode(out),
+            None => out.put_u8(0xc0),
+        }
+    }
+
+    fn length(&self) -> usize {
+        self.as_ref().map_or(1, Encodable::length)
+    }
+}
+
+impl<T: Encodable + ?Sized> Encodable for &T {
+    fn encode(&self, out: &mut dyn BufMut) {
+        (**self).encode(out);
+    }
+
+    fn length(&self) -> usize {
+        (**self).length()
+    }
+}
+
+impl<T: Encodable + ?Sized> Encodable for Box<T> {
+    fn encode(&self, out: &mut dyn BufMut) {
+        (**self).encode(out);
+    }
+
+    fn length(&self) -> usize {
+        (**self).length()
+    }
+}
+
+macro_rules! encodable_tuple {
+    ($($name:ident),+) => {
+        /// Tuples are lists of their elements
+        impl<$($name: Encodable),+> Encodable for ($($name,)+) {
+            #[allow(non_snake_case)]
+            fn encode(&self, out: &mut dyn BufMut) {
+                let ($($name,)+) = self;
+                let payload_len = 0 $(+ $name.length())+;
+                Header { list: true, payload_len }.encode(out);
+                $($name.encode(out);)+
+            }
+
+            #[allow(non_snake_case)]
+            fn length(&self) -> usize {
+                let ($($name,)+) = self;
+                let payload_len = 0 $(+ $name.length())+;
+                payload_len + Header { list: true, payload_len }.length()
+            }
+        }
+    };
+}
+
+encodable_tuple!(A);
+encodable_tuple!(A, B);
+encodable_tuple!(A, B, C);
+encodable_tuple!(A, B, C, D);
+encodable_tuple!(A, B, C, D, E);
+encodable_tuple!(A, B, C, D, E, F);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ser::to_rlp_bytes;
+
+    #[test]
+    fn encode_test() {
+        assert_eq!(encode(&0u64), to_rlp_bytes(&0u64).unwrap());
+        assert_eq!(encode(&0x7fu8), [0x7f]);
+        assert_eq!(encode(&1024u64), to_rlp_bytes(&1024u64).unwrap());
+        assert_eq!(encode(&-1i32), to_rlp_bytes(&-1i32).unwrap());
+        assert_eq!(encode("cat"), to_rlp_bytes("cat").unwrap());
+        assert_eq!(encode(&true), to_rlp_bytes(&true).unwrap());
+
+        let long: Vec<Vec<u64>> = (0..40).map(|i| vec![i, i * 1000]).collect();
+        assert_eq!(encode(&long), to_rlp_bytes(&long).unwrap());
+        assert_eq!(long.length(), encode(&long).len());
+
+        let options = vec![Some(String::from("dog")), None];
+        assert_eq!(encode(&options), to_rlp_bytes(&options).unwrap());
+
+        assert_eq!(encode(&[0xffu8; 32])[..2], [0xa0, 0xff]);
+        assert_eq!(encode(&(1u8, "cat")), [0xc5, 0x01, 0x83, b'c', b'a', b't']);
+    }
+
+    #[cfg(feature = "bytes")]
+    #[test]
+    fn bytes_buf_mut_test() {
+        let mut buf = bytes::BytesMut::new();
+        vec![1u8, 2, 3].encode(&mut buf);
+        Header {
+            list: false,
+            payload_len: 0,
+        }
+        .encode(&mut buf);
+        assert_eq!(&buf[..], [0xc3, 0x01, 0x02, 0x03, 0x80]);
+    }
+}
@@ -0,0 +1,187 @@
+use core::fmt;
+use core::marker::PhantomData;
+use core::ops::{Deref, DerefMut};
+
+use serde::de::{Deserialize, Deserializer, Visitor};
+use serde::ser::{Serialize, Serializer};
+
+use crate::decode::{decode_exact, next_string, Decodable};
+use crate::encode::{bytes_length, encode, encode_bytes, BufMut, Encodable};
+use crate::error::Error;
+use crate::header::Header;
+
+/// Newtype name the serializer and deserializer look for to nest the encoding in a byte string
+pub(crate) const NAME: &str = "$serde_rlp::Encoded";
+
+/// RLP of `T` carried inside a byte string, as for typed transactions in block bodies and
+/// trie node values
+///
+/// Decoding unwraps the byte string and decodes `T` from its contents, which have to be
+/// exactly one item. Limits and depth carry over from the enclosing value.
+///
+/// Two-pass encoders record the length of the wrapped value while measuring, single pass ones
+/// insert the header after writing it. A wrapped value encoding to one byte is serialized once
+/// more to pick its header, so one-shot values like `iter_list` only work here in a single pass
+/// and when they encode to more than one byte.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Encoded<T>(pub T);
+
+impl<T> Encoded<T> {
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> Deref for Encoded<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for Encoded<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
+impl<T> From<T> for Encoded<T> {
+    fn from(value: T) -> Self {
+        Encoded(value)
+    }
+}
+
+impl<T: Serialize> Serialize for Encoded<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_newtype_struct(NAME, &self.0)
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Encoded<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct EncodedVisitor<T>(PhantomData<T>);
+
+        impl<'de, T: Deserialize<'de>> Visitor<'de> for EncodedVisitor<T> {
+            type Value = Encoded<T>;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a byte string holding an encoded item")
+            }
+
+            fn visit_newtype_struct<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+            where
+                D: Deserializer<'de>,
+            {
+                T::deserialize(deserializer).map(Encoded)
+            }
+        }
+
+        deserializer.deserialize_newtype_struct(NAME, EncodedVisitor(PhantomData))
+    }
+}
+
+impl<T: Encodable> Encodable for Encoded<T> {
+    fn encode(&self, out: &mut dyn BufMut) {
+        encode_bytes(&encode(&self.0), out);
+    }
+
+    fn length(&self) -> usize {
+        match self.0.length() {
+            // A single byte below 0x80 is its own encoding, so the byte decides the header
+            1 => bytes_length(&encode(&self.0)),
+            len => {
+                len + Header {
+                    list: false,
+                    payload_len: len,
+                }
+                .length()
+            }
+        }
+    }
+}
+
+impl<T: Decodable> Decodable for Encoded<T> {
+    fn decode(buf: &mut &[u8]) -> Result<Self, Error> {
+        decode_exact(next_string(buf)?).map(Encoded)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::des::from_rlp_bytes;
+    use crate::ser::{to_array, to_rlp_bytes};
+    use core::cell::Cell;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Block {
+        number: u64,
+        transactions: Vec<Encoded<Vec<String>>>,
+        flag: Encoded<u8>,
+    }
+
+    /// Two byte string counting how often it is serialized
+    struct Counted<'a>(&'a Cell<usize>);
+
+    impl Serialize for Counted<'_> {
+        fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            self.0.set(self.0.get() + 1);
+            serializer.serialize_bytes(b"ab")
+        }
+    }
+
+    #[test]
+    fn encoded_test() {
+        let block = Block {
+            number: 1,
+            transactions: vec![Encoded(vec![String::from("cat")])],
+            flag: Encoded(5),
+        };
+        let bytes = to_rlp_bytes(&block).unwrap();
+        // The transaction list c4 83 "cat" is nested in the byte string 85
+        assert_eq!(
+            bytes,
+            [0xc9, 0x01, 0xc6, 0x85, 0xc4, 0x83, b'c', b'a', b't', 0x05]
+        );
+        assert_eq!(from_rlp_bytes::<Block>(&bytes).unwrap(), block);
+
+        // Back-patched list headers around a nested encoding
+        assert_eq!(to_array::<16, _>(&block).unwrap().as_slice(), &bytes[..]);
+
+        assert_eq!(to_rlp_bytes(&Encoded(0u8)).unwrap(), [0x81, 0x80]);
+        assert_eq!(encode(&Encoded(0u8)), [0x81, 0x80]);
+        assert_eq!(
+            Encoded(vec![7u64; 60]).length(),
+            encode(&Encoded(vec![7u64; 60])).len()
+        );
+        assert_eq!(
+            decode_exact::<Encoded<Vec<u64>>>(&encode(&Encoded(vec![1u64, 2]))).unwrap(),
+            Encoded(vec![1, 2])
+        );
+
+        // Nesting serializes the innermost value once per pass
+        let calls = Cell::new(0);
+        let nested = Encoded(Encoded(Encoded(Encoded(Counted(&calls)))));
+        assert_eq!(
+            to_rlp_bytes(&nested).unwrap(),
+            [0x86, 0x85, 0x84, 0x83, 0x82, b'a', b'b']
+        );
+        assert_eq!(calls.get(), 2);
+        assert_eq!(
+            to_array::<8, _>(&nested).unwrap().as_slice(),
+            [0x86, 0x85, 0x84, 0x83, 0x82, b'a', b'b']
+        );
+        assert_eq!(calls.get(), 3);
+
+        // A one byte list is measured in place without throwing off the lengths after it
+        let lists = vec![Encoded(Vec::<u8>::new()), Encoded(vec![1u8])];
+        let bytes = to_rlp_bytes(&lists).unwrap();
+        assert_eq!(bytes, [0xc5, 0x81, 0xc0, 0x82, 0xc1, 0x01]);
+        assert_eq!(to_array::<8, _>(&lists).unwrap().as_slice(), &bytes[..]);
+
+        // Contents must be exactly one item
+        assert!(from_rlp_bytes::<Encoded<u8>>(&[0x82, 0x01, 0x02]).is_err());
+    }
+}
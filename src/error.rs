@@ -1,21 +1,167 @@
+use alloc::boxed::Box;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::error::Error as StdError;
 use core::fmt;
-use std::error::Error as StdError;
+#[cfg(feature = "std")]
+use std::io;
 
 #[derive(Debug)]
-pub struct Error;
+pub enum Error {
+    /// Error reported by a `Serialize` or `Deserialize` implementation
+    Message(String),
+    /// Input ended in the middle of an item
+    Eof,
+    /// Reading or writing the underlying stream failed
+    #[cfg(feature = "std")]
+    Io(io::Error),
+    /// Found a list where a byte string was expected
+    ExpectedString,
+    /// Found a byte string where a list was expected
+    ExpectedList,
+    /// Integer payload does not fit in the requested type
+    IntegerOverflow,
+    /// List has no item at the index
+    IndexOutOfBounds(usize),
+    /// Input is larger or deeper than the configured `Limits` allow
+    LimitExceeded(Limit),
+    /// List holds more items than the type consumed, the count consumed is attached
+    TrailingItems(usize),
+    /// The data model feature is not supported by RLP
+    Unsupported(&'static str),
+    /// Valid item with a shorter encoding, which go-ethereum rejects as well
+    NonCanonical(&'static str),
+    /// Output buffer cannot hold the encoding, nothing was written to it
+    BufferTooSmall { needed: usize, available: usize },
+    /// Error raised while decoding the item at `path`
+    At { path: Path, error: Box<Error> },
+}
+
+impl Error {
+    /// Location of the failing item, if the error was raised inside a list
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            Error::At { path, .. } => Some(path),
+            _ => None,
+        }
+    }
+
+    /// The error without its location
+    pub fn inner(&self) -> &Error {
+        match self {
+            Error::At { error, .. } => error,
+            error => error,
+        }
+    }
+
+    /// Records that the error was raised inside `segment`
+    /// Called while unwinding, so each enclosing list prepends its own segment
+    pub fn within(self, segment: Segment) -> Self {
+        match self {
+            Error::At { mut path, error } => {
+                path.0.insert(0, segment);
+                Error::At { path, error }
+            }
+            error => Error::At {
+                path: Path(vec![segment]),
+                error: Box::new(error),
+            },
+        }
+    }
+}
+
+/// Location of an item inside the decoded value, e.g. `block.transactions[17]`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Path(Vec<Segment>);
+
+impl Path {
+    pub fn segments(&self) -> &[Segment] {
+        &self.0
+    }
+}
+
+/// A single step of a `Path`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Segment {
+    /// Struct field name
+    Field(&'static str),
+    /// Position inside a sequence
+    Index(usize),
+}
+
+impl fmt::Display for Path {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, segment) in self.0.iter().enumerate() {
+            match segment {
+                Segment::Field(name) if i == 0 => f.write_str(name)?,
+                Segment::Field(name) => write!(f, ".{}", name)?,
+                Segment::Index(index) => write!(f, "[{}]", index)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The limit that was hit while decoding
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Limit {
+    /// Lists nested deeper than `Limits::max_depth`
+    Depth,
+    /// A single item longer than `Limits::max_item_len`
+    ItemLength,
+    /// More list elements in total than `Limits::max_list_elements`
+    ListElements,
+}
 
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
-            _ => write!(f, "Failed"),
+            Error::Message(msg) => f.write_str(msg),
+            Error::Eof => f.write_str("unexpected end of input"),
+            #[cfg(feature = "std")]
+            Error::Io(err) => write!(f, "io error: {}", err),
+            Error::ExpectedString => f.write_str("expected byte string, got list"),
+            Error::ExpectedList => f.write_str("expected list, got byte string"),
+            Error::IntegerOverflow => f.write_str("integer does not fit in target type"),
+            Error::IndexOutOfBounds(index) => write!(f, "list has no item at index {}", index),
+            Error::LimitExceeded(Limit::Depth) => f.write_str("maximum nesting depth exceeded"),
+            Error::LimitExceeded(Limit::ItemLength) => f.write_str("maximum item length exceeded"),
+            Error::LimitExceeded(Limit::ListElements) => {
+                f.write_str("maximum number of list elements exceeded")
+            }
+            Error::TrailingItems(count) => write!(f, "list has more than {} items", count),
+            Error::Unsupported(what) => write!(f, "{} is not supported", what),
+            Error::NonCanonical(what) => write!(f, "non-canonical {}", what),
+            Error::BufferTooSmall { needed, available } => write!(
+                f,
+                "buffer too small, {} bytes needed but {} available",
+                needed, available
+            ),
+            Error::At { path, error } => write!(f, "{}: {}", path, error),
         }
     }
 }
 
-impl StdError for Error {}
+impl StdError for Error {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            #[cfg(feature = "std")]
+            Error::Io(err) => Some(err),
+            Error::At { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
 
 impl serde::de::Error for Error {
-    fn custom<T: std::fmt::Display>(_msg: T) -> Self {
-        Error
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Error::Message(msg.to_string())
     }
-}
\ No newline at end of file
+}
+
+impl serde::ser::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Error::Message(msg.to_string())
+    }
+}
@@ -0,0 +1,201 @@
+use crate::encode::BufMut;
+use crate::error::{Error, Limit};
+
+/// Prefix of an encoded item, telling its kind and the length of what follows
+///
+/// Bytes below 0x80 are their own encoding and have no header, `decode` reports them as a
+/// one byte string without consuming anything so the payload always follows the header.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Header {
+    pub list: bool,
+    pub payload_len: usize,
+}
+
+impl Header {
+    /// Decodes the header at the front of `buf` and advances past it
+    /// The payload is not checked to be present, the header is checked to be the shortest one
+    pub fn decode(buf: &mut &[u8]) -> Result<Header, Error> {
+        let prefix = *buf.first().ok_or(Error::Eof)?;
+        let (list, len_of_len) = match prefix {
+            // Single byte is its own payload
+            0x00..=0x7f => {
+                return Ok(Header {
+                    list: false,
+                    payload_len: 1,
+                })
+            }
+            0x80..=0xb7 => {
+                // A single byte below 0x80 is its own encoding
+                if prefix == 0x81 && buf.get(1).is_some_and(|&b| b < 0x80) {
+                    return Err(Error::NonCanonical("single byte string"));
+                }
+                *buf = &buf[1..];
+                return Ok(Header {
+                    list: false,
+                    payload_len: (prefix - 0x80) as usize,
+                });
+            }
+            0xb8..=0xbf => (false, (prefix - 0xb7) as usize),
+            0xc0..=0xf7 => {
+                *buf = &buf[1..];
+                return Ok(Header {
+                    list: true,
+                    payload_len: (prefix - 0xc0) as usize,
+                });
+            }
+            0xf8..=0xff => (true, (prefix - 0xf7) as usize),
+        };
+
+        // Big endian length following a long string or long list prefix
+        let len_bytes = buf.get(1..1 + len_of_len).ok_or(Error::Eof)?;
+        if len_bytes[0] == 0 {
+            return Err(Error::NonCanonical("length with leading zero bytes"));
+        }
+        let payload_len = len_bytes.iter().try_fold(0usize, |acc, &b| {
+            acc.checked_mul(256)
+                .map(|acc| acc | b as usize)
+                .ok_or(Error::LimitExceeded(Limit::ItemLength))
+        })?;
+        if payload_len <= 55 {
+            return Err(Error::NonCanonical("long header for a short payload"));
+        }
+        *buf = &buf[1 + len_of_len..];
+        Ok(Header { list, payload_len })
+    }
+
+    /// Writes the header, callers handle single bytes below 0x80 which take none
+    pub fn encode<B: BufMut + ?Sized>(&self, out: &mut B) {
+        let (buff, len) = self.to_array();
+        out.put_slice(&buff[..len]);
+    }
+
+    /// Number of bytes `encode` writes
+    pub fn length(&self) -> usize {
+        if self.payload_len <= 55 {
+            1
+        } else {
+            1 + Self::len_of_len(self.payload_len)
+        }
+    }
+
+    /// The encoded header in a buffer along with the number of bytes used
+    pub(crate) fn to_array(self) -> ([u8; 9], usize) {
+        let offset = if self.list { 0xc0 } else { 0x80 };
+        let mut buff = [0u8; 9];
+        if self.payload_len <= 55 {
+            buff[0] = offset + self.payload_len as u8;
+            (buff, 1)
+        } else {
+            let size = Self::len_of_len(self.payload_len);
+            buff[0] = offset + 55 + size as u8;
+            buff[1..=size].copy_from_slice(&(self.payload_len as u64).to_be_bytes()[8 - size..]);
+            (buff, size + 1)
+        }
+    }
+
+    fn len_of_len(len: usize) -> usize {
+        8 - (len as u64).leading_zeros() as usize / 8
+    }
+}
+
+/// Splits the first item off `buf`
+/// Returns its header, its payload and the bytes after it
+pub fn split(buf: &[u8]) -> Result<(Header, &[u8], &[u8]), Error> {
+    let mut rest = buf;
+    let header = Header::decode(&mut rest)?;
+    if header.payload_len > rest.len() {
+        return Err(Error::Eof);
+    }
+    let (payload, rest) = rest.split_at(header.payload_len);
+    Ok((header, payload, rest))
+}
+
+/// Splits the first item off `buf`, which has to be a list
+/// Returns the encoded items of the list and the bytes after it
+pub fn split_list(buf: &[u8]) -> Result<(&[u8], &[u8]), Error> {
+    match split(buf)? {
+        (Header { list: true, .. }, payload, rest) => Ok((payload, rest)),
+        _ => Err(Error::ExpectedList),
+    }
+}
+
+/// Splits the first item off `buf`, which has to be a byte string
+/// Returns the contents of the string and the bytes after it
+pub fn split_string(buf: &[u8]) -> Result<(&[u8], &[u8]), Error> {
+    match split(buf)? {
+        (Header { list: false, .. }, payload, rest) => Ok((payload, rest)),
+        _ => Err(Error::ExpectedString),
+    }
+}
+
+/// Number of items encoded back to back in `buf`, such as the payload of a list
+pub fn count_values(mut buf: &[u8]) -> Result<usize, Error> {
+    let mut count = 0;
+    while !buf.is_empty() {
+        let (_, _, rest) = split(buf)?;
+        buf = rest;
+        count += 1;
+    }
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn header_test() {
+        let bytes = [0xc8, 0x83, b'c', b'a', b't', 0x83, b'd', b'o', b'g', 0x05];
+
+        let mut buf = &bytes[..];
+        let header = Header::decode(&mut buf).unwrap();
+        assert_eq!(
+            header,
+            Header {
+                list: true,
+                payload_len: 8
+            }
+        );
+        assert_eq!(buf.len(), 9);
+
+        let (items, rest) = split_list(&bytes).unwrap();
+        assert_eq!(rest, [0x05]);
+        assert_eq!(count_values(items).unwrap(), 2);
+        assert_eq!(split_string(items).unwrap().0, b"cat");
+        assert!(matches!(split_string(&bytes), Err(Error::ExpectedString)));
+        // Single bytes are their own payload
+        assert_eq!(split_string(rest).unwrap(), (&[0x05][..], &[][..]));
+
+        let long = Header {
+            list: false,
+            payload_len: 1024,
+        };
+        let mut out = Vec::new();
+        long.encode(&mut out);
+        assert_eq!(out, [0xb9, 0x04, 0x00]);
+        assert_eq!(long.length(), 3);
+        assert_eq!(Header::decode(&mut &out[..]).unwrap(), long);
+    }
+
+    #[test]
+    fn canonical_test() {
+        let non_canonical: [&[u8]; 4] = [
+            &[0x81, 0x05],
+            &[0xb8, 0x01, 0x05],
+            &[0xb9, 0x00, 0x38],
+            &[0xf8, 0x02, 0x01, 0x02],
+        ];
+        for bytes in non_canonical {
+            assert!(matches!(split(bytes), Err(Error::NonCanonical(_))));
+        }
+        assert_eq!(split(&[0x81, 0x80]).unwrap().1, [0x80]);
+        assert!(matches!(
+            crate::des::from_rlp_bytes::<u64>(&[0x82, 0x00, 0x05]),
+            Err(Error::NonCanonical(_))
+        ));
+        assert!(matches!(
+            crate::decode::decode_exact::<u8>(&[0x00]),
+            Err(Error::NonCanonical(_))
+        ));
+    }
+}
@@ -0,0 +1,93 @@
+use core::cell::Cell;
+use core::fmt;
+
+use serde::ser::{Error as _, Serialize, Serializer};
+
+/// List whose elements come from an iterator, see `iter_list`
+pub struct IterList<I>(Cell<Option<I>>);
+
+/// Encodes the items of `items` as one list without collecting them first
+///
+/// The iterator is consumed by the first serialization, so the list has to be encoded in a
+/// single pass with its header back-patched after the last element, as `to_rlp_bytes_single_pass`
+/// or `to_array` do. Two-pass encoders like `to_rlp_bytes`, `to_writer` or `rlp_hash` fail with
+/// an error instead, and so does `Encoded`. Only the items are not collected, the encoding is
+/// still built in memory because the list header depends on all of it.
+pub fn iter_list<I>(items: I) -> IterList<I::IntoIter>
+where
+    I: IntoIterator,
+    I::Item: Serialize,
+{
+    IterList(Cell::new(Some(items.into_iter())))
+}
+
+impl<I> fmt::Debug for IterList<I> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("IterList")
+    }
+}
+
+impl<I> Serialize for IterList<I>
+where
+    I: Iterator,
+    I::Item: Serialize,
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.0.take() {
+            Some(items) => serializer.collect_seq(items),
+            None => Err(S::Error::custom(
+                "iter_list serialized twice, encode it in a single pass",
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ser::{to_array, to_rlp_bytes, to_rlp_bytes_single_pass};
+    use serde::Serialize;
+
+    #[derive(Serialize, Clone)]
+    struct Entry {
+        key: u64,
+        value: String,
+    }
+
+    #[derive(Serialize)]
+    struct Node<L> {
+        version: u8,
+        entries: L,
+    }
+
+    #[test]
+    fn iter_list_test() {
+        let entries: Vec<Entry> = (0..1000)
+            .map(|key| Entry {
+                key,
+                value: format!("value {key}"),
+            })
+            .collect();
+        let node = Node {
+            version: 1,
+            entries: iter_list(entries.clone()),
+        };
+        let collected = Node {
+            version: 1,
+            entries,
+        };
+        let bytes = to_rlp_bytes_single_pass(&node).unwrap();
+        assert_eq!(bytes, to_rlp_bytes(&collected).unwrap());
+
+        // Nested lists of unknown length
+        let nested = iter_list((0..3u64).map(|i| iter_list(0..i)));
+        assert_eq!(
+            to_array::<16, _>(&nested).unwrap().as_slice(),
+            [0xc6, 0xc0, 0xc1, 0x80, 0xc2, 0x80, 0x01]
+        );
+
+        // The measuring pass already drained the iterator
+        assert!(to_rlp_bytes(&iter_list(0..3u64)).is_err());
+        assert!(to_rlp_bytes_single_pass(&nested).is_err());
+    }
+}
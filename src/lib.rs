@@ -1,4 +1,35 @@
-pub mod ser;
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
+
+pub mod adapter;
+pub mod decode;
 pub mod des;
+pub mod encode;
+pub mod encoded;
 pub mod error;
+pub mod header;
+pub mod iter;
+#[cfg(feature = "keccak")]
+pub mod keccak;
+mod macros;
+pub mod raw;
+pub mod sealed;
+pub mod ser;
+pub mod sink;
+pub mod stream;
+pub mod tail;
+pub mod trailing;
+pub mod value;
+pub mod view;
+
+pub use iter::iter_list;
+
+#[cfg(feature = "derive")]
+pub use serde_rlp_derive::{RlpDecodable, RlpEncodable};
 
+/// Used by the exported macros, not public API
+#[doc(hidden)]
+pub mod __private {
+    pub use alloc::vec;
+}
@@ -0,0 +1,54 @@
+/// Builds an `RlpValue` from a literal-like syntax
+///
+/// Brackets become lists, every other item is converted with `IntoRlpValue`, so integers get
+/// their canonical encoding and strings and byte strings are taken as they are.
+/// Items are single tokens, wrap longer expressions in parentheses.
+///
+/// ```
+/// use serde_rlp::rlp;
+///
+/// let value = rlp!([0x01, "cat", [b"dog", 1024u64], []]);
+/// assert_eq!(value[2][1].as_u64(), Some(1024));
+/// ```
+#[macro_export]
+macro_rules! rlp {
+    ([ $($item:tt),* $(,)? ]) => {
+        $crate::value::RlpValue::List($crate::__private::vec![ $( $crate::rlp!($item) ),* ])
+    };
+    ($item:expr) => {
+        $crate::value::IntoRlpValue::into_rlp_value($item)
+    };
+}
+
+/// Same as `rlp!`, returning the encoded bytes
+///
+/// ```
+/// use serde_rlp::rlp_bytes;
+///
+/// assert_eq!(rlp_bytes!(["cat", "dog"]), [0xc8, 0x83, b'c', b'a', b't', 0x83, b'd', b'o', b'g']);
+/// ```
+#[macro_export]
+macro_rules! rlp_bytes {
+    ($($value:tt)+) => {
+        $crate::ser::to_rlp_bytes(&$crate::rlp!($($value)+)).expect("RlpValue always encodes")
+    };
+}
+
+/// Emits a `tracing` event for an item, `length` being its payload length
+/// Without the `tracing` feature nothing is emitted and the arguments are only borrowed
+macro_rules! trace_item {
+    ($message:literal, $kind:expr, $offset:expr, $length:expr, $depth:expr) => {{
+        #[cfg(feature = "tracing")]
+        tracing::trace!(
+            kind = $kind,
+            offset = $offset,
+            length = $length,
+            depth = $depth,
+            $message
+        );
+        #[cfg(not(feature = "tracing"))]
+        let _ = (&$kind, &$offset, &$length, &$depth);
+    }};
+}
+
+pub(crate) use trace_item;
@@ -0,0 +1,254 @@
+use alloc::vec::Vec;
+use core::fmt;
+use core::marker::PhantomData;
+
+use serde::de::{Deserialize, Deserializer, Visitor};
+use serde::ser::{Serialize, Serializer};
+
+use crate::decode::{next_raw, Decodable};
+use crate::des::from_rlp_bytes;
+use crate::encode::{BufMut, Encodable};
+use crate::error::Error;
+use crate::header::split;
+
+/// Newtype name the serializer and deserializer look for to pass the encoding through
+pub(crate) const NAME: &str = "$serde_rlp::Raw";
+
+/// One already encoded item, embedded verbatim, like `serde_json::value::RawValue`
+///
+/// Serializing appends the bytes as they are, deserializing captures the exact bytes of
+/// one item without decoding it.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RawRlp(Vec<u8>);
+
+/// Borrowed form of `RawRlp`, pointing into the input it was decoded from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RawRlpRef<'a>(&'a [u8]);
+
+/// Checks `bytes` is exactly one item
+fn check_item(bytes: &[u8]) -> Result<(), Error> {
+    let (_, _, rest) = split(bytes)?;
+    if !rest.is_empty() {
+        return Err(Error::TrailingItems(1));
+    }
+    Ok(())
+}
+
+impl RawRlp {
+    /// Wraps the encoding of one item, checking it is exactly one item
+    pub fn new(bytes: Vec<u8>) -> Result<Self, Error> {
+        check_item(&bytes)?;
+        Ok(RawRlp(bytes))
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.0
+    }
+
+    pub fn as_raw_ref(&self) -> RawRlpRef<'_> {
+        RawRlpRef(&self.0)
+    }
+
+    /// Decodes the item
+    pub fn decode<'a, T: Deserialize<'a>>(&'a self) -> Result<T, Error> {
+        from_rlp_bytes(&self.0)
+    }
+}
+
+impl<'a> RawRlpRef<'a> {
+    /// Wraps the encoding of one item, checking it is exactly one item
+    pub fn new(bytes: &'a [u8]) -> Result<Self, Error> {
+        check_item(bytes)?;
+        Ok(RawRlpRef(bytes))
+    }
+
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.0
+    }
+
+    pub fn to_raw_rlp(&self) -> RawRlp {
+        RawRlp(self.0.to_vec())
+    }
+
+    /// Decodes the item
+    pub fn decode<T: Deserialize<'a>>(&self) -> Result<T, Error> {
+        from_rlp_bytes(self.0)
+    }
+}
+
+/// The empty byte string, so the default is still exactly one item
+impl Default for RawRlp {
+    fn default() -> Self {
+        RawRlp(alloc::vec![0x80])
+    }
+}
+
+impl From<RawRlpRef<'_>> for RawRlp {
+    fn from(raw: RawRlpRef<'_>) -> Self {
+        raw.to_raw_rlp()
+    }
+}
+
+/// The encoded bytes, handed to `RlpSerializer` as a byte string it writes verbatim
+pub(crate) struct Verbatim<'a>(pub(crate) &'a [u8]);
+
+impl Serialize for Verbatim<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(self.0)
+    }
+}
+
+impl Serialize for RawRlp {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_newtype_struct(NAME, &Verbatim(&self.0))
+    }
+}
+
+impl Serialize for RawRlpRef<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_newtype_struct(NAME, &Verbatim(self.0))
+    }
+}
+
+/// `RlpDeserializer` hands over the bytes of the next item, other formats a byte string
+struct RawVisitor<T>(PhantomData<T>);
+
+impl<'de> Visitor<'de> for RawVisitor<RawRlp> {
+    type Value = RawRlp;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("an encoded RLP item")
+    }
+
+    fn visit_newtype_struct<D: Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> Result<RawRlp, D::Error> {
+        deserializer.deserialize_byte_buf(self)
+    }
+
+    fn visit_bytes<E: serde::de::Error>(self, v: &[u8]) -> Result<RawRlp, E> {
+        self.visit_byte_buf(v.to_vec())
+    }
+
+    fn visit_byte_buf<E: serde::de::Error>(self, v: Vec<u8>) -> Result<RawRlp, E> {
+        RawRlp::new(v).map_err(E::custom)
+    }
+}
+
+impl<'de> Visitor<'de> for RawVisitor<RawRlpRef<'de>> {
+    type Value = RawRlpRef<'de>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("an encoded RLP item borrowed from the input")
+    }
+
+    fn visit_newtype_struct<D: Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> Result<RawRlpRef<'de>, D::Error> {
+        deserializer.deserialize_bytes(self)
+    }
+
+    fn visit_borrowed_bytes<E: serde::de::Error>(self, v: &'de [u8]) -> Result<RawRlpRef<'de>, E> {
+        RawRlpRef::new(v).map_err(E::custom)
+    }
+}
+
+impl<'de> Deserialize<'de> for RawRlp {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_newtype_struct(NAME, RawVisitor::<RawRlp>(PhantomData))
+    }
+}
+
+impl<'de: 'a, 'a> Deserialize<'de> for RawRlpRef<'a> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_newtype_struct(NAME, RawVisitor::<RawRlpRef<'de>>(PhantomData))
+    }
+}
+
+impl Encodable for RawRlp {
+    fn encode(&self, out: &mut dyn BufMut) {
+        out.put_slice(&self.0);
+    }
+
+    fn length(&self) -> usize {
+        self.0.len()
+    }
+}
+
+impl Encodable for RawRlpRef<'_> {
+    fn encode(&self, out: &mut dyn BufMut) {
+        out.put_slice(self.0);
+    }
+
+    fn length(&self) -> usize {
+        self.0.len()
+    }
+}
+
+impl Decodable for RawRlp {
+    fn decode(buf: &mut &[u8]) -> Result<Self, Error> {
+        next_raw(buf).map(|raw| RawRlp(raw.to_vec()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ser::to_rlp_bytes;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Serialize, Deserialize)]
+    struct Body {
+        number: u64,
+        transactions: Vec<RawRlp>,
+    }
+
+    #[derive(Deserialize)]
+    struct BorrowedBody<'a> {
+        number: u64,
+        #[serde(borrow)]
+        transactions: Vec<RawRlpRef<'a>>,
+    }
+
+    #[test]
+    fn raw_test() {
+        let tx = to_rlp_bytes(&vec![String::from("cat"), String::from("dog")]).unwrap();
+        let body = Body {
+            number: 7,
+            transactions: vec![
+                RawRlp::new(tx.clone()).unwrap(),
+                RawRlp::new(vec![0x05]).unwrap(),
+            ],
+        };
+
+        let bytes = to_rlp_bytes(&body).unwrap();
+        let mut expected = vec![0xc0 + tx.len() as u8 + 3, 0x07, 0xc0 + tx.len() as u8 + 1];
+        expected.extend_from_slice(&tx);
+        expected.push(0x05);
+        assert_eq!(bytes, expected);
+
+        let decoded: Body = from_rlp_bytes(&bytes).unwrap();
+        assert_eq!(decoded.transactions[0].as_bytes(), &tx[..]);
+        assert_eq!(decoded.transactions[1].decode::<u8>().unwrap(), 5);
+
+        let borrowed: BorrowedBody = from_rlp_bytes(&bytes).unwrap();
+        assert_eq!(borrowed.number, 7);
+        assert_eq!(
+            borrowed.transactions[0].as_bytes().as_ptr(),
+            bytes[3..].as_ptr()
+        );
+
+        assert!(RawRlp::new(vec![0x05, 0x06]).is_err());
+        assert!(RawRlp::new(vec![0x82, 0x01]).is_err());
+
+        // The default still serializes to one item
+        assert_eq!(to_rlp_bytes(&RawRlp::default()).unwrap(), [0x80]);
+        assert_eq!(RawRlp::default().decode::<u64>().unwrap(), 0);
+    }
+}
@@ -0,0 +1,188 @@
+use core::fmt;
+use core::hash::{Hash, Hasher};
+use core::marker::PhantomData;
+
+use serde::de::{Deserialize, Deserializer, SeqAccess, Visitor};
+use serde::ser::{Serialize, Serializer};
+#[cfg(all(feature = "keccak", feature = "std"))]
+use std::sync::OnceLock;
+
+use crate::error::Error;
+use crate::raw::RawRlp;
+use crate::ser::to_rlp_bytes;
+
+/// Newtype name the deserializer looks for to hand over the decoded value with its bytes
+pub(crate) const NAME: &str = "$serde_rlp::Sealed";
+
+/// Decoded value along with the exact encoding it was decoded from
+///
+/// Serializing writes the original bytes back unchanged, so hashing a sealed value never
+/// depends on re-encoding it.
+#[derive(Clone)]
+pub struct Sealed<T> {
+    inner: T,
+    raw: RawRlp,
+    #[cfg(all(feature = "keccak", feature = "std"))]
+    hash: OnceLock<[u8; 32]>,
+    /// Without `std` there is no thread-safe cell, the hash is computed up front
+    #[cfg(all(feature = "keccak", not(feature = "std")))]
+    hash: [u8; 32],
+}
+
+impl<T> Sealed<T> {
+    /// Pairs `inner` with its encoding, the caller makes sure they match
+    pub fn new_unchecked(inner: T, raw: RawRlp) -> Self {
+        Sealed {
+            #[cfg(all(feature = "keccak", feature = "std"))]
+            hash: OnceLock::new(),
+            #[cfg(all(feature = "keccak", not(feature = "std")))]
+            hash: crate::keccak::keccak256(raw.as_bytes()),
+            inner,
+            raw,
+        }
+    }
+
+    /// Encodes `inner` and keeps the encoding
+    pub fn new(inner: T) -> Result<Self, Error>
+    where
+        T: Serialize,
+    {
+        let raw = RawRlp::new(to_rlp_bytes(&inner)?)?;
+        Ok(Self::new_unchecked(inner, raw))
+    }
+
+    pub fn inner(&self) -> &T {
+        &self.inner
+    }
+
+    /// The encoding the value was decoded from
+    pub fn raw(&self) -> &[u8] {
+        self.raw.as_bytes()
+    }
+
+    pub fn into_parts(self) -> (T, RawRlp) {
+        (self.inner, self.raw)
+    }
+
+    /// Keccak-256 of the encoding, computed on first use
+    #[cfg(all(feature = "keccak", feature = "std"))]
+    pub fn hash(&self) -> [u8; 32] {
+        *self
+            .hash
+            .get_or_init(|| crate::keccak::keccak256(self.raw.as_bytes()))
+    }
+
+    /// Keccak-256 of the encoding
+    #[cfg(all(feature = "keccak", not(feature = "std")))]
+    pub fn hash(&self) -> [u8; 32] {
+        self.hash
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Sealed<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Sealed")
+            .field("inner", &self.inner)
+            .field("raw", &self.raw)
+            .finish()
+    }
+}
+
+/// Sealed values are equal when their encodings are
+impl<T> PartialEq for Sealed<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.raw == other.raw
+    }
+}
+
+impl<T> Eq for Sealed<T> {}
+
+impl<T> Hash for Sealed<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.raw.hash(state);
+    }
+}
+
+impl<T> Serialize for Sealed<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.raw.serialize(serializer)
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Sealed<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        /// `RlpDeserializer` hands over the decoded value followed by the bytes it spans
+        struct SealedVisitor<T>(PhantomData<T>);
+
+        impl<'de, T: Deserialize<'de>> Visitor<'de> for SealedVisitor<T> {
+            type Value = Sealed<T>;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("an RLP item decoded by RlpDeserializer")
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Sealed<T>, A::Error> {
+                let inner = seq
+                    .next_element()?
+                    .ok_or_else(|| serde::de::Error::invalid_length(0, &self))?;
+                let raw = seq
+                    .next_element()?
+                    .ok_or_else(|| serde::de::Error::invalid_length(1, &self))?;
+                Ok(Sealed::new_unchecked(inner, raw))
+            }
+        }
+
+        deserializer.deserialize_newtype_struct(NAME, SealedVisitor(PhantomData))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::des::from_rlp_bytes;
+    use crate::trailing::Trailing;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Header {
+        number: u64,
+        extra: Vec<u8>,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    struct Block {
+        header: Sealed<Trailing<Header>>,
+        uncles: Vec<Sealed<Trailing<Header>>>,
+    }
+
+    fn assert_sync<T: Sync + Send>() {}
+
+    #[test]
+    fn sealed_test() {
+        assert_sync::<Sealed<Header>>();
+
+        // The header carries an item this version does not know, re-encoding would drop it
+        let header = [0xc6, 0x07, 0xc0, 0x83, b'n', b'e', b'w'];
+        let mut bytes = vec![0xcf];
+        bytes.extend_from_slice(&header);
+        bytes.push(0xc7);
+        bytes.extend_from_slice(&header);
+
+        let block: Block = from_rlp_bytes(&bytes).unwrap();
+        assert_eq!(block.header.inner().number, 7);
+        assert_eq!(block.header.raw(), &header[..]);
+        assert_eq!(block.uncles[0].raw(), &header[..]);
+        assert_eq!(to_rlp_bytes(&block).unwrap(), bytes);
+
+        let sealed = Sealed::new(Trailing(Header {
+            number: 7,
+            extra: vec![],
+        }))
+        .unwrap();
+        assert_eq!(sealed.raw(), [0xc2, 0x07, 0xc0]);
+        assert_eq!(sealed.inner(), block.header.inner());
+        assert_ne!(sealed, block.header);
+        #[cfg(feature = "keccak")]
+        assert_eq!(sealed.hash(), crate::keccak::keccak256(&[0xc2, 0x07, 0xc0]));
+    }
+}
@@ -1,39 +1,317 @@
 use serde::{ser, Serialize};
-use std::error::Error as StdError;
-use std::{
-    fmt::{self, Display},
-    io,
-};
+
+use alloc::format;
+use alloc::string::ToString;
+use alloc::vec::{self, Vec};
+use core::mem;
+#[cfg(feature = "std")]
+use std::io;
+
+use crate::error::Error;
+use crate::header::Header;
+use crate::macros::trace_item;
+#[cfg(feature = "bytes")]
+use crate::sink::BufMutWriter;
+#[cfg(feature = "keccak")]
+use crate::sink::HashWriter;
+#[cfg(feature = "std")]
+use crate::sink::IoWriter;
+use crate::sink::{ArrayBuf, Counter, Sink, SliceBuf};
+use crate::{encoded, raw, tail};
 
 /// Struct that will handle the output of serialization
-pub struct RlpSerializer {
-    pub output: Vec<u8>,
-    pub list_output: Vec<u8>,
-    pub is_list: bool,
+pub struct RlpSerializer<S = Vec<u8>> {
+    pub output: S,
+    headers: ListHeaders,
+    /// `None` struct fields not written yet
+    /// Dropped when the struct ends, written as empty lists before anything that follows them
+    nones: usize,
+    /// First of the pending `None` fields, reported when a `Some` follows it
+    none_field: Option<&'static str>,
+    /// Next value is this struct field, a `None` is kept pending
+    field: Option<&'static str>,
+    /// The struct field just written was a `Some`
+    some: bool,
+    /// Next sequence is a `Tail` and gets no list header
+    tail: bool,
+    /// Number of lists currently open, reported in trace events
+    depth: usize,
+    /// Next byte string is a `RawRlp` and is written without a header
+    raw: bool,
+}
+
+/// How list headers end up in front of their payload
+enum ListHeaders {
+    /// Inserted into the sink once the list ends
+    Insert,
+    /// Inserted as well, every payload length is also recorded in the order lists start
+    Record(Vec<usize>),
+    /// Written up front from the lengths recorded by an earlier pass
+    Replay(vec::IntoIter<usize>),
+}
+
+impl Default for RlpSerializer {
+    fn default() -> Self {
+        RlpSerializer::new(Vec::new())
+    }
+}
+
+/// Encodes `value` into a buffer allocated at the exact size
+///
+/// The value is serialized twice, first to measure every list so the second pass can write
+/// each header ahead of its payload without moving any bytes. One-shot sequences such as
+/// `iter_list` are drained by the first pass and fail here, encode them with
+/// `to_rlp_bytes_single_pass`.
+pub fn to_rlp_bytes<T>(value: &T) -> Result<Vec<u8>, Error>
+where
+    T: ?Sized + Serialize,
+{
+    let (len, lengths) = measure(value)?;
+    let mut serializer = RlpSerializer::with_headers(
+        Vec::with_capacity(len),
+        ListHeaders::Replay(lengths.into_iter()),
+    );
+    value.serialize(&mut serializer)?;
+    serializer.into_output()
+}
+
+/// Encodes `value` in a single pass, back-patching each list header once the list ends
+///
+/// Needed for values that can only be serialized once, like `iter_list`. Every list header
+/// moves the payload after it, which is one move of the whole output for a long flat list.
+pub fn to_rlp_bytes_single_pass<T>(value: &T) -> Result<Vec<u8>, Error>
+where
+    T: ?Sized + Serialize,
+{
+    let mut serializer = RlpSerializer::new(Vec::new());
+    value.serialize(&mut serializer)?;
+    serializer.into_output()
+}
+
+/// Encodes `value` into the front of `buf` and returns the number of bytes written
+///
+/// Fails with `Error::BufferTooSmall` before writing anything if `buf` is too short.
+/// Measures the value in a first pass, use `to_array` for values that serialize only once.
+pub fn to_slice<T>(value: &T, buf: &mut [u8]) -> Result<usize, Error>
+where
+    T: ?Sized + Serialize,
+{
+    let (len, lengths) = measure(value)?;
+    if len > buf.len() {
+        return Err(Error::BufferTooSmall {
+            needed: len,
+            available: buf.len(),
+        });
+    }
+    let mut serializer =
+        RlpSerializer::with_headers(SliceBuf::new(buf), ListHeaders::Replay(lengths.into_iter()));
+    value.serialize(&mut serializer)?;
+    serializer.flush_none()?;
+    Ok(len)
+}
+
+/// Encodes `value` into a fixed-capacity buffer without touching the heap
+///
+/// Single pass, list headers are back-patched inside the buffer once each list ends.
+/// Fails with `Error::BufferTooSmall` if the encoding does not fit in `N` bytes.
+pub fn to_array<const N: usize, T>(value: &T) -> Result<ArrayBuf<N>, Error>
+where
+    T: ?Sized + Serialize,
+{
+    let mut serializer = RlpSerializer::new(ArrayBuf::new());
+    value.serialize(&mut serializer)?;
+    serializer.into_output()
+}
+
+/// Appends the encoding of `value` to `out` and returns the number of bytes appended
+///
+/// Reserves the exact length up front so pooled buffers are reused without reallocating.
+/// On error `out` is left as it was. The exact length comes from a measuring pass, so
+/// one-shot values like `iter_list` fail here.
+pub fn to_vec_into<T>(value: &T, out: &mut Vec<u8>) -> Result<usize, Error>
+where
+    T: ?Sized + Serialize,
+{
+    let (len, lengths) = measure(value)?;
+    let original_len = out.len();
+    out.reserve(len);
+    let mut serializer =
+        RlpSerializer::with_headers(&mut *out, ListHeaders::Replay(lengths.into_iter()));
+    let result = value
+        .serialize(&mut serializer)
+        .and_then(|()| serializer.flush_none());
+    if let Err(err) = result {
+        out.truncate(original_len);
+        return Err(err);
+    }
+    Ok(len)
+}
+
+/// Puts the encoding of `value` into `buf` and returns the number of bytes written
+///
+/// Fails with `Error::BufferTooSmall` before writing anything if `buf` cannot take it all.
+/// The value is serialized twice, so it must not be a one-shot value like `iter_list`.
+#[cfg(feature = "bytes")]
+pub fn to_buf_mut<T, B>(value: &T, buf: &mut B) -> Result<usize, Error>
+where
+    T: ?Sized + Serialize,
+    B: bytes::BufMut,
+{
+    let (len, lengths) = measure(value)?;
+    if len > buf.remaining_mut() {
+        return Err(Error::BufferTooSmall {
+            needed: len,
+            available: buf.remaining_mut(),
+        });
+    }
+    let mut serializer = RlpSerializer::with_headers(
+        BufMutWriter::new(buf),
+        ListHeaders::Replay(lengths.into_iter()),
+    );
+    value.serialize(&mut serializer)?;
+    serializer.flush_none()?;
+    Ok(len)
+}
+
+/// Encoded length of `value` in bytes, without writing the encoding anywhere
+pub fn encoded_len<T>(value: &T) -> Result<usize, Error>
+where
+    T: ?Sized + Serialize,
+{
+    let mut serializer = RlpSerializer::new(Counter::default());
+    value.serialize(&mut serializer)?;
+    Ok(serializer.into_output()?.0)
+}
+
+/// Keccak-256 of the encoding of `value`
+///
+/// The encoding is streamed into the hasher as it is produced and never held in memory,
+/// like `to_writer` only the list lengths are kept between the two passes.
+/// One-shot values like `iter_list` fail, hash the output of `to_rlp_bytes_single_pass` instead.
+#[cfg(feature = "keccak")]
+pub fn rlp_hash<T>(value: &T) -> Result<[u8; 32], Error>
+where
+    T: ?Sized + Serialize,
+{
+    let (_, lengths) = measure(value)?;
+    let mut serializer = RlpSerializer::with_headers(
+        HashWriter::default(),
+        ListHeaders::Replay(lengths.into_iter()),
+    );
+    value.serialize(&mut serializer)?;
+    Ok(serializer.into_output()?.finalize())
+}
+
+/// Streams the encoding of `value` into `writer`
+///
+/// Like `to_rlp_bytes` the value is serialized twice, only the list lengths are kept in memory.
+/// Many small writes are issued, wrap unbuffered writers in `io::BufWriter`.
+/// One-shot values like `iter_list` fail, write the output of `to_rlp_bytes_single_pass` instead.
+#[cfg(feature = "std")]
+pub fn to_writer<W, T>(writer: W, value: &T) -> Result<(), Error>
+where
+    W: io::Write,
+    T: ?Sized + Serialize,
+{
+    let (_, lengths) = measure(value)?;
+    let mut serializer = RlpSerializer::with_headers(
+        IoWriter::new(writer),
+        ListHeaders::Replay(lengths.into_iter()),
+    );
+    value.serialize(&mut serializer)?;
+    serializer.flush_none()
+}
+
+/// Length recorded for the next list or `Encoded` by the measuring pass
+fn next_length(lengths: &mut vec::IntoIter<usize>) -> Result<usize, Error> {
+    lengths
+        .next()
+        .ok_or_else(|| Error::Message("value serialized differently between passes".into()))
+}
+
+/// Header of the byte string around the `len` byte encoding of `value`
+fn encoded_header<T>(value: &T, len: usize) -> Result<([u8; 9], usize), Error>
+where
+    T: ?Sized + Serialize,
+{
+    if len == 1 {
+        // A single byte below 0x80 is its own encoding, the byte decides the header
+        let byte = to_array::<1, _>(value)?;
+        if byte[0] < 0x80 {
+            return Ok(([0; 9], 0));
+        }
+    }
+    Ok(Header {
+        list: false,
+        payload_len: len,
+    }
+    .to_array())
 }
 
-pub fn to_rlp_bytes<T>(value: &T) -> Result<Vec<u8>, ()>
+/// First pass of a two-pass encode
+/// Returns the encoded length and the payload length of every list in the order lists start
+fn measure<T>(value: &T) -> Result<(usize, Vec<usize>), Error>
 where
-    T: Serialize,
+    T: ?Sized + Serialize,
 {
-    let mut serializer = RlpSerializer {
-        output: Vec::new(),
-        list_output: Vec::new(),
-        is_list: false,
-    };
-    value.serialize(&mut serializer).unwrap();
-    Ok(serializer.output)
+    let mut measure =
+        RlpSerializer::with_headers(Counter::default(), ListHeaders::Record(Vec::new()));
+    value.serialize(&mut measure)?;
+    measure.flush_none()?;
+    match measure.headers {
+        ListHeaders::Record(lengths) => Ok((measure.output.0, lengths)),
+        _ => unreachable!(),
+    }
 }
 
-impl RlpSerializer {
+impl<S: Sink> RlpSerializer<S> {
+    /// Serializer writing into `output`, which has to support inserting list headers
+    pub fn new(output: S) -> Self {
+        Self::with_headers(output, ListHeaders::Insert)
+    }
+
+    fn with_headers(output: S, headers: ListHeaders) -> Self {
+        RlpSerializer {
+            output,
+            headers,
+            nones: 0,
+            none_field: None,
+            field: None,
+            some: false,
+            tail: false,
+            depth: 0,
+            raw: false,
+        }
+    }
+
+    /// Writes out anything still pending and returns the output
+    pub fn into_output(mut self) -> Result<S, Error> {
+        self.flush_none()?;
+        Ok(self.output)
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> Result<(), Error> {
+        self.flush_none()?;
+        self.output.write(bytes)
+    }
+
+    /// Writes the pending `None` struct fields, a value follows them in the list
+    fn flush_none(&mut self) -> Result<(), Error> {
+        self.field = None;
+        self.none_field = None;
+        for _ in 0..mem::take(&mut self.nones) {
+            self.output.write(&[0xc0])?;
+        }
+        Ok(())
+    }
+
     pub fn serialize_number(&mut self, v: u64) -> Result<(), Error> {
+        self.trace_string(8 - v.leading_zeros() as usize / 8)?;
         match v {
-            0 => {
-                self.output.push(0x80);
-            }
+            0 => self.write(&[0x80]),
             1..=127 => {
                 //Single byte
-                self.output.push(v as u8);
+                self.write(&[v as u8])
             }
             _ => {
                 // More than a single byte
@@ -42,140 +320,140 @@ impl RlpSerializer {
                 let l = self.write_bytes(v, &mut buff[1..]);
                 buff[0] = 0x80u8 + l;
 
-                self.output
-                    .extend_from_slice(&buff[0usize..l as usize + 1 as usize]);
+                self.write(&buff[0usize..l as usize + 1])
             }
         }
-        Ok(())
     }
 
-    /// Returns the total number of bytes written to the array that is provided
-    fn write_bytes(&self, v: u64, buff: &mut [u8]) -> u8 {
-        // Can Represent v as 1 to 8 bytes
-        // Calculate how many byte does v requires
-        match v {
-            // 255 - 1 byte
-            v if v < (v << 8) => {
-                buff[0] = v as u8;
-                1
-            }
-            // 2 byte
-            v if v < (v << 16) => {
-                buff[0] = (v >> 8) as u8;
-                buff[1] = v as u8;
-                2
-            }
-            // 3 byte
-            v if v < (v << 24) => {
-                buff[0] = (v >> 16) as u8;
-                buff[1] = (v >> 8) as u8;
-                buff[2] = v as u8;
-                3
-            }
-            // 4 bytes
-            v if v < (v << 32) => {
-                buff[0] = (v >> 24) as u8;
-                buff[1] = (v >> 16) as u8;
-                buff[2] = (v >> 8) as u8;
-                buff[3] = v as u8;
-                4
-            }
-            // 5 bytes
-            v if v < (v << 40) => {
-                buff[0] = (v >> 32) as u8;
-                buff[1] = (v >> 24) as u8;
-                buff[2] = (v >> 16) as u8;
-                buff[3] = (v >> 8) as u8;
-                buff[4] = v as u8;
-                5
-            }
-            // 6 bytes
-            v if v < (v << 48) => {
-                buff[0] = (v >> 40) as u8;
-                buff[1] = (v >> 32) as u8;
-                buff[2] = (v >> 24) as u8;
-                buff[3] = (v >> 16) as u8;
-                buff[4] = (v >> 8) as u8;
-                buff[5] = v as u8;
-                6
-            }
-            // 7 bytes
-            v if v < (v << 56) => {
-                buff[0] = (v >> 48) as u8;
-                buff[1] = (v >> 40) as u8;
-                buff[2] = (v >> 32) as u8;
-                buff[3] = (v >> 24) as u8;
-                buff[4] = (v >> 16) as u8;
-                buff[5] = (v >> 8) as u8;
-                buff[6] = v as u8;
-                7
-            }
-            // 8 Bytes
-            _ => {
-                buff[0] = (v >> 56) as u8;
-                buff[1] = (v >> 48) as u8;
-                buff[2] = (v >> 40) as u8;
-                buff[3] = (v >> 32) as u8;
-                buff[4] = (v >> 24) as u8;
-                buff[5] = (v >> 16) as u8;
-                buff[6] = (v >> 8) as u8;
-                buff[7] = v as u8;
-                8
+    /// Writes the encoding of `value` as one byte string
+    /// Its length is recorded while measuring like a list's, a single pass inserts the header
+    /// once the value is written
+    fn serialize_encoded<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        self.flush_none()?;
+        if let ListHeaders::Replay(lengths) = &mut self.headers {
+            let len = next_length(lengths)?;
+            self.trace_string(len)?;
+            let (buff, size) = encoded_header(value, len)?;
+            self.output.write(&buff[..size])?;
+            value.serialize(&mut *self)?;
+            return self.flush_none();
+        }
+
+        let slot = match &mut self.headers {
+            ListHeaders::Record(lengths) => {
+                lengths.push(0);
+                lengths.len() - 1
             }
+            _ => 0,
+        };
+        let start = self.output.position();
+        value.serialize(&mut *self)?;
+        self.flush_none()?;
+        let len = self.output.position() - start;
+        match &mut self.headers {
+            ListHeaders::Record(lengths) => lengths[slot] = len,
+            _ => trace_item!("encode", "string", start, len, self.depth),
+        }
+        let (buff, size) = encoded_header(value, len)?;
+        if size > 0 {
+            self.output.insert(start, &buff[..size])?;
         }
+        Ok(())
     }
-}
-
-#[derive(Debug)]
-pub struct Error;
 
-impl fmt::Display for Error {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            _ => write!(f, "Failed"),
+    /// Reports the byte string about to be written
+    /// Offsets are positions in the output, with `Insert` headers of open lists not there yet.
+    /// The measuring pass of a two-pass encode stays quiet.
+    fn trace_string(&mut self, payload_len: usize) -> Result<(), Error> {
+        self.flush_none()?;
+        if !matches!(self.headers, ListHeaders::Record(_)) {
+            trace_item!(
+                "encode",
+                "string",
+                self.output.position(),
+                payload_len,
+                self.depth
+            );
         }
+        Ok(())
+    }
+
+    /// Returns the total number of bytes written to the array that is provided
+    fn write_bytes(&self, v: u64, buff: &mut [u8]) -> u8 {
+        // Can Represent v as 1 to 8 bytes, leading zero bytes are dropped
+        let len = 8 - v.leading_zeros() as usize / 8;
+        buff[..len].copy_from_slice(&v.to_be_bytes()[8 - len..]);
+        len as u8
     }
-}
 
-impl StdError for Error {}
+    /// Called when a list starts, returns its slot in the recorded lengths
+    fn start_list(&mut self) -> Result<usize, Error> {
+        self.flush_none()?;
+        let slot = match &mut self.headers {
+            ListHeaders::Insert => 0,
+            ListHeaders::Record(lengths) => {
+                lengths.push(0);
+                lengths.len() - 1
+            }
+            ListHeaders::Replay(lengths) => {
+                let len = next_length(lengths)?;
+                trace_item!("encode", "list", self.output.position(), len, self.depth);
+                let (buff, size) = Header {
+                    list: true,
+                    payload_len: len,
+                }
+                .to_array();
+                self.output.write(&buff[..size])?;
+                0
+            }
+        };
+        self.depth += 1;
+        Ok(slot)
+    }
 
-impl serde::ser::Error for Error {
-    fn custom<T: std::fmt::Display>(_msg: T) -> Self {
-        Error
+    /// Puts the list header in front of the payload written since `start`
+    fn finish_list(&mut self, start: usize, slot: usize) -> Result<(), Error> {
+        self.flush_none()?;
+        self.depth -= 1;
+        let len = self.output.position() - start;
+        match &mut self.headers {
+            ListHeaders::Replay(_) => return Ok(()),
+            ListHeaders::Record(lengths) => lengths[slot] = len,
+            ListHeaders::Insert => trace_item!("encode", "list", start, len, self.depth),
+        }
+        let (buff, size) = Header {
+            list: true,
+            payload_len: len,
+        }
+        .to_array();
+        self.output.insert(start, &buff[..size])
     }
 }
 
-impl<'a> serde::ser::Serializer for &'a mut RlpSerializer {
+impl<'a, S: Sink> serde::ser::Serializer for &'a mut RlpSerializer<S> {
     type Ok = ();
 
     // The error type when some error occurs during serialization.
     type Error = Error;
 
-    type SerializeSeq = Self;
-    type SerializeTuple = Self;
-    type SerializeTupleStruct = Self;
-    type SerializeTupleVariant = Self;
-    type SerializeMap = Self;
-    type SerializeStruct = Self;
-    type SerializeStructVariant = Self;
+    type SerializeSeq = Compound<'a, S>;
+    type SerializeTuple = Compound<'a, S>;
+    type SerializeTupleStruct = Compound<'a, S>;
+    type SerializeTupleVariant = Compound<'a, S>;
+    type SerializeMap = Compound<'a, S>;
+    type SerializeStruct = Compound<'a, S>;
+    type SerializeStructVariant = Compound<'a, S>;
 
     fn serialize_bool(self, v: bool) -> Result<Self::Ok, Self::Error> {
         // The encoded value is the byte of the boolean itself
-        if v {
-            self.output.push(0x01);
-        } else {
-            self.output.push(0x80);
-        }
-        Ok(())
+        self.serialize_bytes(if v { &[0x01] } else { &[] })
     }
 
     fn serialize_i8(self, v: i8) -> Result<Self::Ok, Self::Error> {
-        println!("Serializing I8");
         self.serialize_number(v as u64)
     }
 
     fn serialize_u8(self, v: u8) -> Result<Self::Ok, Self::Error> {
-        println!("Serializing U74");
         self.serialize_number(v as u64)
     }
 
@@ -200,7 +478,7 @@ impl<'a> serde::ser::Serializer for &'a mut RlpSerializer {
     }
 
     fn serialize_u64(self, v: u64) -> Result<Self::Ok, Self::Error> {
-        self.serialize_number(v as u64)
+        self.serialize_number(v)
     }
 
     fn serialize_f32(self, v: f32) -> Result<Self::Ok, Self::Error> {
@@ -216,58 +494,38 @@ impl<'a> serde::ser::Serializer for &'a mut RlpSerializer {
     fn serialize_str(self, v: &str) -> Result<Self::Ok, Self::Error> {
         //Convert string to byte array
         let str_bytes = v.as_bytes();
-        println!("Ser STR");
         self.serialize_bytes(str_bytes)
     }
 
     fn serialize_bytes(self, v: &[u8]) -> Result<Self::Ok, Self::Error> {
-        if v.len() == 1 && v[0] < 0x7f {
+        if mem::take(&mut self.raw) {
+            // Already a complete item
+            return self.write(v);
+        }
+        self.trace_string(v.len())?;
+        if v.len() == 1 && v[0] <= 0x7f {
             //Single byte
-            if self.is_list {
-                self.list_output.push(v[0]);
-            } else {
-                self.output.push(v[0]);
-            }
-        } else if v.len() < 55 {
-            //This constitutes of empty array if string is just empty ("")
-            let prefix = 0x80u8 + v.len() as u8;
-            if self.is_list {
-                self.list_output.push(prefix);
-                self.list_output.extend_from_slice(v);
-            } else {
-                self.output.push(prefix);
-                self.output.extend_from_slice(v);
-            }
+            self.write(&v[..1])
         } else {
-            let mut buff = [0u8; 9];
-            let size_str_len_bytes = self.write_bytes(v.len() as u64, &mut buff[1..]);
-            buff[0] = 0xB7 + size_str_len_bytes;
-
-            if self.is_list {
-                //Write both the Prefix (Added with size_str_len_bytes) and the length of the string (the byte representation)
-                self.list_output
-                    .extend_from_slice(&buff[..size_str_len_bytes as usize + 1 as usize]);
-                //Append the actual string
-                self.list_output.extend_from_slice(v);
-            } else {
-                //Write both the Prefix (Added with size_str_len_bytes) and the length of the string (the byte representation)
-                self.output
-                    .extend_from_slice(&buff[..size_str_len_bytes as usize + 1 as usize]);
-                //Append the actual string
-                self.output.extend_from_slice(v);
+            //This constitutes of empty array if string is just empty ("")
+            //Longer strings carry the length of the string after the prefix
+            let (buff, len) = Header {
+                list: false,
+                payload_len: v.len(),
             }
+            .to_array();
+            self.write(&buff[..len])?;
+            self.write(v)
         }
-
-        Ok(())
     }
 
     fn serialize_i128(self, v: i128) -> Result<Self::Ok, Self::Error> {
         self.serialize_u128(v as u128)
     }
 
-    fn serialize_u128(self, v: u128) -> Result<Self::Ok, Self::Error> {
+    fn serialize_u128(self, _v: u128) -> Result<Self::Ok, Self::Error> {
         //TODO: Implementation left
-        return Err(Error);
+        Err(Error::Unsupported("u128"))
     }
 
     fn serialize_char(self, v: char) -> Result<Self::Ok, Self::Error> {
@@ -277,12 +535,16 @@ impl<'a> serde::ser::Serializer for &'a mut RlpSerializer {
     }
     fn serialize_unit(self) -> Result<Self::Ok, Self::Error> {
         //Emtpy List
-        self.output.push(0xc0);
-
-        Ok(())
+        self.write(&[0xc0])
     }
 
+    /// Written lazily as a struct field, so it can still be left out at the end of the list
     fn serialize_none(self) -> Result<Self::Ok, Self::Error> {
+        if let Some(field) = self.field.take() {
+            self.nones += 1;
+            self.none_field.get_or_insert(field);
+            return Ok(());
+        }
         self.serialize_unit()
     }
 
@@ -290,30 +552,35 @@ impl<'a> serde::ser::Serializer for &'a mut RlpSerializer {
     where
         T: ?Sized + Serialize,
     {
-        value.serialize(self)
+        let field = self.field.take();
+        value.serialize(&mut *self)?;
+        // Set after the value, whose own struct fields reset it
+        self.some = field.is_some();
+        Ok(())
     }
 
+    /// Structs are lists of their fields
     fn serialize_struct(
         self,
-        name: &'static str,
-        len: usize,
+        _name: &'static str,
+        _len: usize,
     ) -> Result<Self::SerializeStruct, Self::Error> {
-        Ok(self)
+        Compound::new(self, false)
     }
 
-    fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq, Self::Error> {
-        // Notify to other serializer function that going forward its going to serialize a list
-        // The end of serializer will be in end() function of SerializeSeq
-        self.is_list = true;
-        Ok(self)
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, Self::Error> {
+        // The list header is written in end() function of SerializeSeq once the payload length is known
+        // so the length hint is not needed, sequences of unknown length are encoded the same way
+        let flat = mem::take(&mut self.tail);
+        Compound::new(self, flat)
     }
 
-    fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap, Self::Error> {
-        Err(Error)
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, Self::Error> {
+        Err(Error::Unsupported("map"))
     }
 
-    fn serialize_unit_struct(self, name: &'static str) -> Result<Self::Ok, Self::Error> {
-        Err(Error)
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Self::Ok, Self::Error> {
+        Err(Error::Unsupported("unit struct"))
     }
 
     fn serialize_newtype_struct<T>(
@@ -324,65 +591,128 @@ impl<'a> serde::ser::Serializer for &'a mut RlpSerializer {
     where
         T: ?Sized + Serialize,
     {
-        Err(Error)
+        if name == raw::NAME {
+            self.raw = true;
+            let result = value.serialize(&mut *self);
+            self.raw = false;
+            return result;
+        }
+        if name == tail::NAME {
+            // A plain field, the elements are not optional fields themselves
+            self.field = None;
+            self.tail = true;
+            let result = value.serialize(&mut *self);
+            self.tail = false;
+            return result;
+        }
+        if name == encoded::NAME {
+            return self.serialize_encoded(value);
+        }
+        // Newtypes are transparent
+        value.serialize(self)
     }
 
+    /// Variants holding data are a list of the variant index followed by the fields
     fn serialize_newtype_variant<T>(
         self,
-        name: &'static str,
+        _name: &'static str,
         variant_index: u32,
-        variant: &'static str,
+        _variant: &'static str,
         value: &T,
     ) -> Result<Self::Ok, Self::Error>
     where
         T: ?Sized + Serialize,
     {
-        Err(Error)
+        let compound = Compound::variant(self, variant_index)?;
+        value.serialize(&mut *compound.ser)?;
+        compound.end()
     }
 
     fn serialize_struct_variant(
         self,
-        name: &'static str,
+        _name: &'static str,
         variant_index: u32,
-        variant: &'static str,
-        len: usize,
+        _variant: &'static str,
+        _len: usize,
     ) -> Result<Self::SerializeStructVariant, Self::Error> {
-        Err(Error)
+        Compound::variant(self, variant_index)
     }
 
-    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple, Self::Error> {
-        Err(Error)
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, Self::Error> {
+        Err(Error::Unsupported("tuple"))
     }
 
     fn serialize_tuple_struct(
         self,
-        name: &'static str,
-        len: usize,
+        _name: &'static str,
+        _len: usize,
     ) -> Result<Self::SerializeTupleStruct, Self::Error> {
-        Err(Error)
+        Err(Error::Unsupported("tuple struct"))
     }
 
     fn serialize_tuple_variant(
         self,
-        name: &'static str,
+        _name: &'static str,
         variant_index: u32,
-        variant: &'static str,
-        len: usize,
+        _variant: &'static str,
+        _len: usize,
     ) -> Result<Self::SerializeTupleVariant, Self::Error> {
-        Err(Error)
+        Compound::variant(self, variant_index)
     }
 
+    /// Unit variants are their index
     fn serialize_unit_variant(
         self,
-        name: &'static str,
+        _name: &'static str,
         variant_index: u32,
-        variant: &'static str,
+        _variant: &'static str,
     ) -> Result<Self::Ok, Self::Error> {
-        Err(Error)
+        self.serialize_number(variant_index as u64)
+    }
+}
+
+/// State of a list being serialized
+pub struct Compound<'a, S> {
+    ser: &'a mut RlpSerializer<S>,
+    /// Output position where the list payload starts
+    start: usize,
+    /// Slot of the list in the recorded lengths
+    slot: usize,
+    /// Elements belong to the enclosing list, no header is written
+    flat: bool,
+    /// Struct field set after a `None` one, an error unless a plain field follows
+    set_after_none: Option<(&'static str, &'static str)>,
+}
+
+impl<'a, S: Sink> Compound<'a, S> {
+    fn new(ser: &'a mut RlpSerializer<S>, flat: bool) -> Result<Self, Error> {
+        let slot = if flat { 0 } else { ser.start_list()? };
+        let start = ser.output.position();
+        Ok(Compound {
+            ser,
+            start,
+            slot,
+            flat,
+            set_after_none: None,
+        })
+    }
+
+    /// List of an enum variant, starting with its index
+    fn variant(ser: &'a mut RlpSerializer<S>, variant_index: u32) -> Result<Self, Error> {
+        let compound = Compound::new(ser, false)?;
+        compound.ser.serialize_number(variant_index as u64)?;
+        Ok(compound)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        if self.flat {
+            return self.ser.flush_none();
+        }
+        self.ser.finish_list(self.start, self.slot)
     }
 }
 
-impl<'a> ser::SerializeSeq for &'a mut RlpSerializer {
+impl<S: Sink> ser::SerializeSeq for Compound<'_, S> {
     type Ok = ();
 
     type Error = Error;
@@ -391,23 +721,15 @@ impl<'a> ser::SerializeSeq for &'a mut RlpSerializer {
     where
         T: ?Sized + Serialize,
     {
-        value.serialize(&mut **self)
+        value.serialize(&mut *self.ser)
     }
 
     fn end(self) -> Result<Self::Ok, Self::Error> {
-        let prefix = 0xc0 + self.list_output.len() as u8;
-
-        self.output.push(prefix);
-        self.output.extend_from_slice(&self.list_output);
-
-        //Flush list output
-        self.list_output.clear();
-        self.is_list = false;
-        Ok(())
+        Compound::end(self)
     }
 }
 
-impl<'a> ser::SerializeTuple for &'a mut RlpSerializer {
+impl<S: Sink> ser::SerializeTuple for Compound<'_, S> {
     type Ok = ();
     type Error = Error;
 
@@ -415,7 +737,7 @@ impl<'a> ser::SerializeTuple for &'a mut RlpSerializer {
     where
         T: ?Sized + Serialize,
     {
-        value.serialize(&mut **self)
+        value.serialize(&mut *self.ser)
     }
 
     fn end(self) -> Result<Self::Ok, Self::Error> {
@@ -423,7 +745,7 @@ impl<'a> ser::SerializeTuple for &'a mut RlpSerializer {
     }
 }
 
-impl<'a> ser::SerializeTupleStruct for &'a mut RlpSerializer {
+impl<S: Sink> ser::SerializeTupleStruct for Compound<'_, S> {
     type Ok = ();
     type Error = Error;
 
@@ -431,7 +753,7 @@ impl<'a> ser::SerializeTupleStruct for &'a mut RlpSerializer {
     where
         T: ?Sized + Serialize,
     {
-        value.serialize(&mut **self)
+        value.serialize(&mut *self.ser)
     }
 
     fn end(self) -> Result<Self::Ok, Self::Error> {
@@ -439,7 +761,7 @@ impl<'a> ser::SerializeTupleStruct for &'a mut RlpSerializer {
     }
 }
 
-impl<'a> ser::SerializeTupleVariant for &'a mut RlpSerializer {
+impl<S: Sink> ser::SerializeTupleVariant for Compound<'_, S> {
     type Ok = ();
     type Error = Error;
 
@@ -447,15 +769,15 @@ impl<'a> ser::SerializeTupleVariant for &'a mut RlpSerializer {
     where
         T: ?Sized + Serialize,
     {
-        value.serialize(&mut **self)
+        value.serialize(&mut *self.ser)
     }
 
     fn end(self) -> Result<Self::Ok, Self::Error> {
-        Ok(())
+        Compound::end(self)
     }
 }
 
-impl<'a> ser::SerializeMap for &'a mut RlpSerializer {
+impl<S: Sink> ser::SerializeMap for Compound<'_, S> {
     type Ok = ();
     type Error = Error;
 
@@ -463,14 +785,14 @@ impl<'a> ser::SerializeMap for &'a mut RlpSerializer {
     where
         T: ?Sized + Serialize,
     {
-        key.serialize(&mut **self)
+        key.serialize(&mut *self.ser)
     }
 
     fn serialize_value<T>(&mut self, value: &T) -> Result<Self::Ok, Self::Error>
     where
         T: ?Sized + Serialize,
     {
-        value.serialize(&mut **self)
+        value.serialize(&mut *self.ser)
     }
 
     fn end(self) -> Result<Self::Ok, Self::Error> {
@@ -478,42 +800,67 @@ impl<'a> ser::SerializeMap for &'a mut RlpSerializer {
     }
 }
 
-impl<'a> ser::SerializeStruct for &'a mut RlpSerializer {
+impl<S: Sink> ser::SerializeStruct for Compound<'_, S> {
     type Ok = ();
     type Error = Error;
 
+    /// Trailing `None` fields are left out of the list, like go-ethereum's `rlp:"optional"`
+    /// A `None` followed by a later field is written as an empty list. Among the trailing
+    /// optional fields a `Some` after a `None` is an error, as in go-ethereum.
     fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<Self::Ok, Self::Error>
     where
         T: ?Sized + Serialize,
     {
-        value.serialize(&mut **self)
+        let none_field = self.ser.none_field;
+        let nones = self.ser.nones;
+        self.ser.field = Some(key);
+        value.serialize(&mut *self.ser)?;
+        self.ser.field = None;
+        if mem::take(&mut self.ser.some) {
+            if let Some(none_field) = none_field {
+                self.set_after_none.get_or_insert((key, none_field));
+            }
+        } else if self.ser.nones <= nones {
+            // Neither `None` nor `Some`, the optional fields before it are not trailing
+            self.set_after_none = None;
+        }
+        Ok(())
     }
 
     fn end(self) -> Result<Self::Ok, Self::Error> {
-        Ok(())
+        if let Some((field, none_field)) = self.set_after_none {
+            return Err(Error::Message(format!(
+                "field `{}` is set after optional field `{}` is None",
+                field, none_field
+            )));
+        }
+        // `None` fields still pending are trailing
+        self.ser.nones = 0;
+        self.ser.none_field = None;
+        Compound::end(self)
     }
 }
 
-impl<'a> ser::SerializeStructVariant for &'a mut RlpSerializer {
+impl<S: Sink> ser::SerializeStructVariant for Compound<'_, S> {
     type Ok = ();
     type Error = Error;
 
-    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<Self::Ok, Self::Error>
+    fn serialize_field<T>(&mut self, _key: &'static str, value: &T) -> Result<Self::Ok, Self::Error>
     where
         T: ?Sized + Serialize,
     {
-        key.serialize(&mut **self)?;
-        value.serialize(&mut **self)
+        value.serialize(&mut *self.ser)
     }
 
     fn end(self) -> Result<Self::Ok, Self::Error> {
-        Ok(())
+        Compound::end(self)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::tail::Tail;
     use serde::Serialize;
 
     #[derive(Serialize)]
@@ -527,8 +874,240 @@ mod tests {
             x: vec![String::from("cat"), String::from("dog")],
         };
 
-        let bytes = to_rlp_bytes(&point);
+        let bytes = to_rlp_bytes(&point).unwrap();
 
-        println!("{:?}", bytes);
+        assert_eq!(bytes, [201, 200, 131, 99, 97, 116, 131, 100, 111, 103]);
+    }
+
+    #[derive(Serialize)]
+    struct Header {
+        number: u64,
+        base_fee: Option<u64>,
+        withdrawals_root: Option<Vec<u8>>,
+    }
+
+    #[test]
+    fn optional_fields_test() {
+        let legacy = Header {
+            number: 5,
+            base_fee: None,
+            withdrawals_root: None,
+        };
+        assert_eq!(to_rlp_bytes(&legacy).unwrap(), [0xc1, 0x05]);
+
+        let london = Header {
+            base_fee: Some(1000),
+            ..legacy
+        };
+        assert_eq!(
+            to_rlp_bytes(&london).unwrap(),
+            [0xc4, 0x05, 0x82, 0x03, 0xe8]
+        );
+
+        let invalid = Header {
+            number: 5,
+            base_fee: None,
+            withdrawals_root: Some(vec![]),
+        };
+        assert!(to_array::<8, _>(&invalid).is_err());
+
+        // A `None` before a plain field is an empty list, like a contract creation's `to`
+        let creation = Transaction {
+            to: None,
+            value: 5,
+            data: Header {
+                number: 5,
+                base_fee: Some(1),
+                withdrawals_root: None,
+            },
+        };
+        assert_eq!(
+            to_rlp_bytes(&creation).unwrap(),
+            [0xc5, 0xc0, 0x05, 0xc2, 0x05, 0x01]
+        );
+        assert_eq!(
+            to_array::<8, _>(&creation).unwrap().as_slice(),
+            [0xc5, 0xc0, 0x05, 0xc2, 0x05, 0x01]
+        );
+
+        // Only a `Some` after a `None` among the trailing optional fields is an error
+        let mid = Mid {
+            a: None,
+            b: Some(1),
+            c: 2,
+        };
+        let bytes = to_rlp_bytes(&mid).unwrap();
+        assert_eq!(bytes, [0xc3, 0xc0, 0x01, 0x02]);
+        assert_eq!(crate::des::from_rlp_bytes::<Mid>(&bytes).unwrap(), mid);
+        let tailed = Tailed {
+            a: None,
+            t: Tail(vec![Some(1)]),
+        };
+        assert_eq!(to_rlp_bytes(&tailed).unwrap(), [0xc2, 0xc0, 0x01]);
+        let err = to_rlp_bytes(&invalid).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "field `withdrawals_root` is set after optional field `base_fee` is None"
+        );
+    }
+
+    #[derive(Serialize)]
+    struct Transaction {
+        to: Option<u64>,
+        value: u64,
+        data: Header,
+    }
+
+    #[derive(Serialize, serde::Deserialize, Debug, PartialEq)]
+    struct Mid {
+        a: Option<u8>,
+        b: Option<u8>,
+        c: u8,
+    }
+
+    #[derive(Serialize)]
+    struct Tailed {
+        a: Option<u8>,
+        t: Tail<Option<u8>>,
+    }
+
+    #[derive(Serialize)]
+    struct Versioned {
+        version: u8,
+        rest: Tail<u64>,
+    }
+
+    #[test]
+    fn tail_test() {
+        let record = Versioned {
+            version: 1,
+            rest: Tail(vec![2, 1024]),
+        };
+        assert_eq!(
+            to_rlp_bytes(&record).unwrap(),
+            [0xc5, 0x01, 0x02, 0x82, 0x04, 0x00]
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[derive(Serialize)]
+    struct Segment {
+        headers: Vec<Header>,
+        names: Vec<Option<String>>,
+        record: Versioned,
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn to_writer_test() {
+        let segment = Segment {
+            headers: (0..40)
+                .map(|number| Header {
+                    number,
+                    base_fee: Some(number * 1000).filter(|_| number % 2 == 0),
+                    withdrawals_root: None,
+                })
+                .collect(),
+            names: vec![Some(String::from("cat")), None],
+            record: Versioned {
+                version: 1,
+                rest: Tail(vec![2, 1024]),
+            },
+        };
+
+        let mut written = Vec::new();
+        to_writer(&mut written, &segment).unwrap();
+
+        assert_eq!(written, to_rlp_bytes(&segment).unwrap());
+    }
+
+    #[test]
+    fn to_slice_test() {
+        let point = Point {
+            x: vec![String::from("cat"), String::from("dog")],
+        };
+        let expected = to_rlp_bytes(&point).unwrap();
+
+        let mut buf = [0u8; 16];
+        let len = to_slice(&point, &mut buf).unwrap();
+        assert_eq!(&buf[..len], &expected[..]);
+
+        let mut small = [0u8; 4];
+        assert!(matches!(
+            to_slice(&point, &mut small),
+            Err(Error::BufferTooSmall {
+                needed: 10,
+                available: 4
+            })
+        ));
+        assert_eq!(small, [0; 4]);
+
+        let mut pooled = vec![0xff];
+        assert_eq!(to_vec_into(&point, &mut pooled).unwrap(), expected.len());
+        assert_eq!(pooled[0], 0xff);
+        assert_eq!(&pooled[1..], &expected[..]);
+    }
+
+    #[test]
+    fn to_array_test() {
+        let point = Point {
+            x: vec![String::from("cat"), String::from("dog")],
+        };
+        let nested = vec![vec![vec![7u64; 30]; 3]; 2];
+
+        let buf = to_array::<16, _>(&point).unwrap();
+        assert_eq!(&buf[..], &to_rlp_bytes(&point).unwrap()[..]);
+        let buf = to_array::<256, _>(&nested).unwrap();
+        assert_eq!(&buf[..], &to_rlp_bytes(&nested).unwrap()[..]);
+        assert!(matches!(
+            to_array::<8, _>(&point),
+            Err(Error::BufferTooSmall { available: 8, .. })
+        ));
+
+        let mut stack = [0u8; 16];
+        let mut serializer = RlpSerializer::new(SliceBuf::new(&mut stack));
+        point.serialize(&mut serializer).unwrap();
+        let written = serializer.into_output().unwrap();
+        assert_eq!(written.as_slice(), &to_rlp_bytes(&point).unwrap()[..]);
+    }
+
+    #[cfg(feature = "bytes")]
+    #[test]
+    fn to_buf_mut_test() {
+        let mut buf = bytes::BytesMut::new();
+        to_buf_mut(&vec![1u8, 2, 3], &mut buf).unwrap();
+        assert_eq!(&buf[..], [0xc3, 0x01, 0x02, 0x03]);
+
+        let mut fixed = [0u8; 2];
+        assert!(to_buf_mut(&vec![1u8, 2, 3], &mut &mut fixed[..]).is_err());
+    }
+
+    #[test]
+    fn encoded_len_test() {
+        let nested = vec![vec![vec![String::from("cat"); 20]; 10]; 3];
+        let bytes = to_rlp_bytes(&nested).unwrap();
+
+        assert_eq!(encoded_len(&nested).unwrap(), bytes.len());
+        assert_eq!(bytes.capacity(), bytes.len());
+        assert_eq!(encoded_len(&Some(5u8)).unwrap(), 1);
+        assert_eq!(encoded_len(&None::<u8>).unwrap(), 1);
+    }
+
+    #[cfg(feature = "keccak")]
+    #[test]
+    fn rlp_hash_test() {
+        use crate::keccak::keccak256;
+
+        let nested = vec![vec![vec![String::from("cat"); 20]; 10]; 3];
+        let bytes = to_rlp_bytes(&nested).unwrap();
+        assert_eq!(rlp_hash(&nested).unwrap(), keccak256(&bytes));
+
+        // Struct with a trailing `None` left out
+        let legacy = Header {
+            number: 5,
+            base_fee: None,
+            withdrawals_root: None,
+        };
+        assert_eq!(rlp_hash(&legacy).unwrap(), keccak256(&[0xc1, 0x05]));
     }
 }
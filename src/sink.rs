@@ -0,0 +1,292 @@
+use alloc::vec::Vec;
+use core::fmt;
+use core::ops::Deref;
+#[cfg(feature = "std")]
+use std::io;
+
+use crate::error::Error;
+#[cfg(feature = "keccak")]
+use crate::keccak::Keccak256;
+
+/// Destination of the bytes produced by `RlpSerializer`
+pub trait Sink {
+    fn write(&mut self, bytes: &[u8]) -> Result<(), Error>;
+
+    /// Number of bytes written so far
+    fn position(&self) -> usize;
+
+    /// Inserts `bytes` at `at`, moving everything written after it
+    /// Sinks that cannot go back only support serializers with precomputed list lengths
+    fn insert(&mut self, _at: usize, _bytes: &[u8]) -> Result<(), Error> {
+        Err(Error::Unsupported("inserting list headers into this sink"))
+    }
+}
+
+impl Sink for Vec<u8> {
+    fn write(&mut self, bytes: &[u8]) -> Result<(), Error> {
+        self.extend_from_slice(bytes);
+        Ok(())
+    }
+
+    fn position(&self) -> usize {
+        self.len()
+    }
+
+    fn insert(&mut self, at: usize, bytes: &[u8]) -> Result<(), Error> {
+        self.splice(at..at, bytes.iter().copied());
+        Ok(())
+    }
+}
+
+impl<S: Sink + ?Sized> Sink for &mut S {
+    fn write(&mut self, bytes: &[u8]) -> Result<(), Error> {
+        (**self).write(bytes)
+    }
+
+    fn position(&self) -> usize {
+        (**self).position()
+    }
+
+    fn insert(&mut self, at: usize, bytes: &[u8]) -> Result<(), Error> {
+        (**self).insert(at, bytes)
+    }
+}
+
+/// Counts the bytes instead of storing them
+#[derive(Debug, Default)]
+pub(crate) struct Counter(pub usize);
+
+impl Sink for Counter {
+    fn write(&mut self, bytes: &[u8]) -> Result<(), Error> {
+        self.0 += bytes.len();
+        Ok(())
+    }
+
+    fn position(&self) -> usize {
+        self.0
+    }
+
+    fn insert(&mut self, _at: usize, bytes: &[u8]) -> Result<(), Error> {
+        self.0 += bytes.len();
+        Ok(())
+    }
+}
+
+/// Forwards the bytes to an `io::Write`
+#[cfg(feature = "std")]
+pub(crate) struct IoWriter<W> {
+    writer: W,
+    written: usize,
+}
+
+#[cfg(feature = "std")]
+impl<W: io::Write> IoWriter<W> {
+    pub(crate) fn new(writer: W) -> Self {
+        IoWriter { writer, written: 0 }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<W: io::Write> Sink for IoWriter<W> {
+    fn write(&mut self, bytes: &[u8]) -> Result<(), Error> {
+        self.writer.write_all(bytes).map_err(Error::Io)?;
+        self.written += bytes.len();
+        Ok(())
+    }
+
+    fn position(&self) -> usize {
+        self.written
+    }
+}
+
+/// Fills a caller provided slice from the front, never allocates
+///
+/// List headers are inserted by moving the payload within the slice, so `RlpSerializer::new`
+/// works on it without precomputed lengths. Running out of room is `Error::BufferTooSmall`,
+/// the contents are unspecified after an error.
+pub struct SliceBuf<'a> {
+    buf: &'a mut [u8],
+    len: usize,
+}
+
+impl<'a> SliceBuf<'a> {
+    pub fn new(buf: &'a mut [u8]) -> Self {
+        SliceBuf { buf, len: 0 }
+    }
+
+    /// The bytes written so far
+    pub fn as_slice(&self) -> &[u8] {
+        &self.buf[..self.len]
+    }
+
+    pub fn into_slice(self) -> &'a mut [u8] {
+        &mut self.buf[..self.len]
+    }
+}
+
+impl Sink for SliceBuf<'_> {
+    fn write(&mut self, bytes: &[u8]) -> Result<(), Error> {
+        self.len = write_within(self.buf, self.len, bytes)?;
+        Ok(())
+    }
+
+    fn position(&self) -> usize {
+        self.len
+    }
+
+    fn insert(&mut self, at: usize, bytes: &[u8]) -> Result<(), Error> {
+        self.len = insert_within(self.buf, self.len, at, bytes)?;
+        Ok(())
+    }
+}
+
+/// Fixed-capacity buffer holding up to `N` encoded bytes inline, never allocates
+///
+/// Same as `SliceBuf` but owns its storage, so it can be returned from the stack.
+#[derive(Clone, Copy)]
+pub struct ArrayBuf<const N: usize> {
+    buf: [u8; N],
+    len: usize,
+}
+
+impl<const N: usize> ArrayBuf<N> {
+    pub fn new() -> Self {
+        ArrayBuf {
+            buf: [0; N],
+            len: 0,
+        }
+    }
+
+    pub fn as_slice(&self) -> &[u8] {
+        &self.buf[..self.len]
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+}
+
+impl<const N: usize> Default for ArrayBuf<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> Deref for ArrayBuf<N> {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        self.as_slice()
+    }
+}
+
+impl<const N: usize> fmt::Debug for ArrayBuf<N> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.as_slice().fmt(f)
+    }
+}
+
+impl<const N: usize> Sink for ArrayBuf<N> {
+    fn write(&mut self, bytes: &[u8]) -> Result<(), Error> {
+        self.len = write_within(&mut self.buf, self.len, bytes)?;
+        Ok(())
+    }
+
+    fn position(&self) -> usize {
+        self.len
+    }
+
+    fn insert(&mut self, at: usize, bytes: &[u8]) -> Result<(), Error> {
+        self.len = insert_within(&mut self.buf, self.len, at, bytes)?;
+        Ok(())
+    }
+}
+
+/// Appends `bytes` after the first `len` bytes of `buf`, returns the new length
+fn write_within(buf: &mut [u8], len: usize, bytes: &[u8]) -> Result<usize, Error> {
+    let end = len + bytes.len();
+    let available = buf.len();
+    let dest = buf.get_mut(len..end).ok_or(Error::BufferTooSmall {
+        needed: end,
+        available,
+    })?;
+    dest.copy_from_slice(bytes);
+    Ok(end)
+}
+
+/// Inserts `bytes` at `at`, moving the bytes after it towards the end, returns the new length
+fn insert_within(buf: &mut [u8], len: usize, at: usize, bytes: &[u8]) -> Result<usize, Error> {
+    let end = len + bytes.len();
+    if end > buf.len() {
+        return Err(Error::BufferTooSmall {
+            needed: end,
+            available: buf.len(),
+        });
+    }
+    buf.copy_within(at..len, at + bytes.len());
+    buf[at..at + bytes.len()].copy_from_slice(bytes);
+    Ok(end)
+}
+
+/// Forwards the bytes to a `bytes::BufMut`
+#[cfg(feature = "bytes")]
+pub(crate) struct BufMutWriter<'a, B> {
+    buf: &'a mut B,
+    written: usize,
+}
+
+#[cfg(feature = "bytes")]
+impl<'a, B: bytes::BufMut> BufMutWriter<'a, B> {
+    pub(crate) fn new(buf: &'a mut B) -> Self {
+        BufMutWriter { buf, written: 0 }
+    }
+}
+
+#[cfg(feature = "bytes")]
+impl<B: bytes::BufMut> Sink for BufMutWriter<'_, B> {
+    fn write(&mut self, bytes: &[u8]) -> Result<(), Error> {
+        self.buf.put_slice(bytes);
+        self.written += bytes.len();
+        Ok(())
+    }
+
+    fn position(&self) -> usize {
+        self.written
+    }
+}
+
+/// Feeds the bytes to a Keccak-256 hasher instead of storing them
+#[cfg(feature = "keccak")]
+#[derive(Default)]
+pub(crate) struct HashWriter {
+    hasher: Keccak256,
+    written: usize,
+}
+
+#[cfg(feature = "keccak")]
+impl HashWriter {
+    pub(crate) fn finalize(self) -> [u8; 32] {
+        self.hasher.finalize()
+    }
+}
+
+#[cfg(feature = "keccak")]
+impl Sink for HashWriter {
+    fn write(&mut self, bytes: &[u8]) -> Result<(), Error> {
+        self.hasher.update(bytes);
+        self.written += bytes.len();
+        Ok(())
+    }
+
+    fn position(&self) -> usize {
+        self.written
+    }
+}
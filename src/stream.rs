@@ -0,0 +1,147 @@
+use alloc::vec::Vec;
+
+use serde::ser::{Serialize, Serializer};
+
+use crate::error::Error;
+use crate::header::Header;
+use crate::ser::RlpSerializer;
+
+/// Builder for encoding data imperatively, such as trie nodes and signing payloads
+///
+/// Produces the same bytes as `to_rlp_bytes` on the equivalent value.
+///
+/// ```
+/// use serde_rlp::stream::RlpStream;
+///
+/// let mut stream = RlpStream::new();
+/// stream.begin_list().append_bytes(b"cat").append_u64(1024);
+/// stream.finalize_list();
+/// assert_eq!(stream.out(), [0xc7, 0x83, b'c', b'a', b't', 0x82, 0x04, 0x00]);
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct RlpStream {
+    buf: Vec<u8>,
+    /// Payload start of every list that is not finalized yet
+    open_lists: Vec<usize>,
+}
+
+impl RlpStream {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Starts a list, items appended until `finalize_list` go inside it
+    pub fn begin_list(&mut self) -> &mut Self {
+        self.open_lists.push(self.buf.len());
+        self
+    }
+
+    /// Writes the header of the innermost open list
+    /// Panics if no list is open
+    pub fn finalize_list(&mut self) -> &mut Self {
+        let start = self.open_lists.pop().expect("no open list to finalize");
+        let header = Header {
+            list: true,
+            payload_len: self.buf.len() - start,
+        };
+        let (buff, len) = header.to_array();
+        self.buf.splice(start..start, buff[..len].iter().copied());
+        self
+    }
+
+    /// Appends any serde value as one item
+    pub fn append<T>(&mut self, value: &T) -> Result<&mut Self, Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        let mut serializer = RlpSerializer::new(&mut self.buf);
+        value.serialize(&mut serializer)?;
+        serializer.into_output()?;
+        Ok(self)
+    }
+
+    pub fn append_bytes(&mut self, bytes: &[u8]) -> &mut Self {
+        let mut serializer = RlpSerializer::new(&mut self.buf);
+        serializer
+            .serialize_bytes(bytes)
+            .expect("writing to a Vec cannot fail");
+        self
+    }
+
+    pub fn append_u64(&mut self, v: u64) -> &mut Self {
+        let mut serializer = RlpSerializer::new(&mut self.buf);
+        serializer
+            .serialize_number(v)
+            .expect("writing to a Vec cannot fail");
+        self
+    }
+
+    /// Appends an already encoded item verbatim
+    pub fn append_raw(&mut self, encoded: &[u8]) -> &mut Self {
+        self.buf.extend_from_slice(encoded);
+        self
+    }
+
+    /// Whether every list begun has been finalized
+    pub fn is_finished(&self) -> bool {
+        self.open_lists.is_empty()
+    }
+
+    /// The encoded bytes
+    /// Panics if a list is still open
+    pub fn out(self) -> Vec<u8> {
+        assert!(self.is_finished(), "list was not finalized");
+        self.buf
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ser::to_rlp_bytes;
+    use serde::Serialize;
+
+    #[derive(Serialize)]
+    struct Node {
+        key: String,
+        children: Vec<Vec<u64>>,
+        value: Option<u64>,
+    }
+
+    #[test]
+    fn stream_test() {
+        let node = Node {
+            key: String::from("dog"),
+            children: vec![vec![1, 1024], vec![]],
+            value: None,
+        };
+
+        let mut stream = RlpStream::new();
+        stream.begin_list().append_bytes(b"dog").begin_list();
+        stream.append(&vec![1u64, 1024]).unwrap();
+        stream.begin_list().finalize_list().finalize_list();
+        stream.finalize_list();
+        assert_eq!(stream.out(), to_rlp_bytes(&node).unwrap());
+
+        let long: Vec<u64> = (0..100).collect();
+        let mut stream = RlpStream::new();
+        stream.begin_list();
+        for v in &long {
+            stream.append_u64(*v);
+        }
+        stream.finalize_list();
+        let encoded = stream.out();
+        assert_eq!(encoded, to_rlp_bytes(&long).unwrap());
+
+        let mut stream = RlpStream::new();
+        stream
+            .begin_list()
+            .append_raw(&encoded)
+            .append(&node)
+            .unwrap();
+        stream.finalize_list();
+        let both = stream.out();
+        let (items, _) = crate::header::split_list(&both).unwrap();
+        assert!(items.starts_with(&encoded));
+    }
+}
@@ -0,0 +1,73 @@
+use alloc::vec::Vec;
+use core::fmt;
+use core::marker::PhantomData;
+use core::ops::{Deref, DerefMut};
+
+use serde::de::{Deserialize, Deserializer, Visitor};
+use serde::ser::{Serialize, Serializer};
+
+/// Newtype name the serializer and deserializer look for to flatten the list
+pub(crate) const NAME: &str = "$serde_rlp::Tail";
+
+/// Elements spliced into the enclosing list, like go-ethereum's `rlp:"tail"`
+///
+/// A `Tail<T>` field has no list header of its own. Encoding appends its elements to the
+/// struct's list and decoding collects every remaining element of that list, so it must be
+/// the last field of the struct.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Tail<T>(pub Vec<T>);
+
+impl<T> Tail<T> {
+    pub fn into_inner(self) -> Vec<T> {
+        self.0
+    }
+}
+
+impl<T> Deref for Tail<T> {
+    type Target = Vec<T>;
+
+    fn deref(&self) -> &Vec<T> {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for Tail<T> {
+    fn deref_mut(&mut self) -> &mut Vec<T> {
+        &mut self.0
+    }
+}
+
+impl<T> From<Vec<T>> for Tail<T> {
+    fn from(value: Vec<T>) -> Self {
+        Tail(value)
+    }
+}
+
+impl<T: Serialize> Serialize for Tail<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_newtype_struct(NAME, &self.0)
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Tail<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct TailVisitor<T>(PhantomData<T>);
+
+        impl<'de, T: Deserialize<'de>> Visitor<'de> for TailVisitor<T> {
+            type Value = Tail<T>;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("the remaining items of a list")
+            }
+
+            fn visit_newtype_struct<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+            where
+                D: Deserializer<'de>,
+            {
+                Vec::deserialize(deserializer).map(Tail)
+            }
+        }
+
+        deserializer.deserialize_newtype_struct(NAME, TailVisitor(PhantomData))
+    }
+}
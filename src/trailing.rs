@@ -0,0 +1,73 @@
+use core::fmt;
+use core::marker::PhantomData;
+use core::ops::{Deref, DerefMut};
+
+use serde::de::{Deserialize, Deserializer, Visitor};
+use serde::ser::{Serialize, Serializer};
+
+/// Newtype name the deserializer looks for to relax arity checking
+pub(crate) const NAME: &str = "$serde_rlp::Trailing";
+
+/// Opts a type into EIP-8 style forward compatibility
+///
+/// Decoding `Trailing<T>` ignores any items after the ones `T` consumes from its list,
+/// so newer peers can append fields without breaking older ones.
+/// Lists are otherwise required to hold exactly as many items as the struct has fields.
+/// Encoding is unchanged.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Trailing<T>(pub T);
+
+impl<T> Trailing<T> {
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> Deref for Trailing<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for Trailing<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
+impl<T> From<T> for Trailing<T> {
+    fn from(value: T) -> Self {
+        Trailing(value)
+    }
+}
+
+impl<T: Serialize> Serialize for Trailing<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.0.serialize(serializer)
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Trailing<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct TrailingVisitor<T>(PhantomData<T>);
+
+        impl<'de, T: Deserialize<'de>> Visitor<'de> for TrailingVisitor<T> {
+            type Value = Trailing<T>;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a list with optional trailing items")
+            }
+
+            fn visit_newtype_struct<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+            where
+                D: Deserializer<'de>,
+            {
+                T::deserialize(deserializer).map(Trailing)
+            }
+        }
+
+        deserializer.deserialize_newtype_struct(NAME, TrailingVisitor(PhantomData))
+    }
+}
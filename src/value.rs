@@ -0,0 +1,250 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+use core::ops::Index;
+
+use serde::de::{self, Deserialize, Deserializer, SeqAccess, Visitor};
+use serde::ser::{Serialize, SerializeSeq, Serializer};
+
+use crate::des::from_rlp_bytes;
+use crate::error::Error;
+use crate::ser::to_rlp_bytes;
+
+/// Any RLP item, for data whose schema is not known up front
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum RlpValue {
+    Bytes(Vec<u8>),
+    List(Vec<RlpValue>),
+}
+
+/// Converts `value` into the tree of its encoding
+pub fn to_value<T>(value: &T) -> Result<RlpValue, Error>
+where
+    T: ?Sized + Serialize,
+{
+    from_rlp_bytes(&to_rlp_bytes(value)?)
+}
+
+/// Decodes a `T` from the tree, as if from its encoding
+pub fn from_value<T>(value: RlpValue) -> Result<T, Error>
+where
+    T: de::DeserializeOwned,
+{
+    from_rlp_bytes(&to_rlp_bytes(&value)?)
+}
+
+impl RlpValue {
+    pub fn is_list(&self) -> bool {
+        matches!(self, RlpValue::List(_))
+    }
+
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            RlpValue::Bytes(bytes) => Some(bytes),
+            RlpValue::List(_) => None,
+        }
+    }
+
+    pub fn as_list(&self) -> Option<&[RlpValue]> {
+        match self {
+            RlpValue::List(items) => Some(items),
+            RlpValue::Bytes(_) => None,
+        }
+    }
+
+    /// Byte string read as a big endian integer of at most 8 bytes
+    pub fn as_u64(&self) -> Option<u64> {
+        match self.as_bytes()? {
+            bytes if bytes.len() <= 8 => {
+                Some(bytes.iter().fold(0, |acc, &b| (acc << 8) | b as u64))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Item at `index` of a list
+/// Panics if the value is a byte string or the index is out of bounds, like indexing a `Vec`
+impl Index<usize> for RlpValue {
+    type Output = RlpValue;
+
+    fn index(&self, index: usize) -> &RlpValue {
+        match self {
+            RlpValue::List(items) => &items[index],
+            RlpValue::Bytes(_) => panic!("cannot index into a byte string"),
+        }
+    }
+}
+
+impl From<Vec<u8>> for RlpValue {
+    fn from(bytes: Vec<u8>) -> Self {
+        RlpValue::Bytes(bytes)
+    }
+}
+
+impl From<Vec<RlpValue>> for RlpValue {
+    fn from(items: Vec<RlpValue>) -> Self {
+        RlpValue::List(items)
+    }
+}
+
+/// Conversion applied to the items of `rlp!`
+pub trait IntoRlpValue {
+    fn into_rlp_value(self) -> RlpValue;
+}
+
+impl IntoRlpValue for RlpValue {
+    fn into_rlp_value(self) -> RlpValue {
+        self
+    }
+}
+
+macro_rules! into_rlp_value_int {
+    ($($inttype:ty),*) => {
+        $(
+            /// Encoded by `RlpSerializer::serialize_number`, so it stays canonical
+            impl IntoRlpValue for $inttype {
+                fn into_rlp_value(self) -> RlpValue {
+                    to_value(&self).expect("integers always encode")
+                }
+            }
+        )*
+    };
+}
+
+into_rlp_value_int!(u8, u16, u32, u64, usize, i8, i16, i32, i64, isize);
+
+/// `true` is the byte 0x01 and `false` the empty string, as `RlpSerializer::serialize_bool` writes
+impl IntoRlpValue for bool {
+    fn into_rlp_value(self) -> RlpValue {
+        to_value(&self).expect("booleans always encode")
+    }
+}
+
+impl IntoRlpValue for &str {
+    fn into_rlp_value(self) -> RlpValue {
+        RlpValue::Bytes(self.as_bytes().to_vec())
+    }
+}
+
+impl IntoRlpValue for String {
+    fn into_rlp_value(self) -> RlpValue {
+        RlpValue::Bytes(self.into_bytes())
+    }
+}
+
+impl IntoRlpValue for &[u8] {
+    fn into_rlp_value(self) -> RlpValue {
+        RlpValue::Bytes(self.to_vec())
+    }
+}
+
+impl<const N: usize> IntoRlpValue for &[u8; N] {
+    fn into_rlp_value(self) -> RlpValue {
+        RlpValue::Bytes(self.to_vec())
+    }
+}
+
+impl IntoRlpValue for Vec<u8> {
+    fn into_rlp_value(self) -> RlpValue {
+        RlpValue::Bytes(self)
+    }
+}
+
+impl Serialize for RlpValue {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            RlpValue::Bytes(bytes) => serializer.serialize_bytes(bytes),
+            RlpValue::List(items) => {
+                let mut seq = serializer.serialize_seq(Some(items.len()))?;
+                for item in items {
+                    seq.serialize_element(item)?;
+                }
+                seq.end()
+            }
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for RlpValue {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct ValueVisitor;
+
+        impl<'de> Visitor<'de> for ValueVisitor {
+            type Value = RlpValue;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a byte string or a list")
+            }
+
+            fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<RlpValue, E> {
+                Ok(RlpValue::Bytes(v.to_vec()))
+            }
+
+            fn visit_byte_buf<E: de::Error>(self, v: Vec<u8>) -> Result<RlpValue, E> {
+                Ok(RlpValue::Bytes(v))
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<RlpValue, A::Error> {
+                let mut items = Vec::new();
+                while let Some(item) = seq.next_element()? {
+                    items.push(item);
+                }
+                Ok(RlpValue::List(items))
+            }
+        }
+
+        deserializer.deserialize_any(ValueVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Transfer {
+        nonce: u64,
+        to: String,
+        data: Vec<u8>,
+    }
+
+    #[test]
+    fn value_test() {
+        let transfer = Transfer {
+            nonce: 1024,
+            to: String::from("cat"),
+            data: vec![0x01, 0x02],
+        };
+
+        let value = to_value(&transfer).unwrap();
+        assert_eq!(value[0].as_u64(), Some(1024));
+        assert_eq!(value[1].as_bytes(), Some(&b"cat"[..]));
+        // Vec<u8> goes through serde as a sequence
+        assert_eq!(value[2].as_list().map(|items| items.len()), Some(2));
+        assert_eq!(
+            to_rlp_bytes(&value).unwrap(),
+            to_rlp_bytes(&transfer).unwrap()
+        );
+
+        assert_eq!(from_value::<Transfer>(value).unwrap(), transfer);
+    }
+
+    #[test]
+    fn rlp_macro_test() {
+        let value = crate::rlp!([0x01, "cat", [b"dog", 1024u64], []]);
+
+        assert_eq!(value[2][1], RlpValue::Bytes(vec![0x04, 0x00]));
+        assert_eq!(
+            crate::rlp_bytes!([0x01, "cat", [b"dog", 1024u64], []]),
+            [
+                0xce, 0x01, 0x83, b'c', b'a', b't', 0xc7, 0x83, b'd', b'o', b'g', 0x82, 0x04, 0x00,
+                0xc0
+            ]
+        );
+        assert_eq!(crate::rlp!(0), RlpValue::Bytes(vec![]));
+        assert_eq!(crate::rlp!(true), RlpValue::Bytes(vec![0x01]));
+        assert_eq!(crate::rlp!(false), RlpValue::Bytes(vec![]));
+    }
+}
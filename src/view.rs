@@ -0,0 +1,158 @@
+use serde::de::Deserialize;
+
+use crate::des::from_rlp_bytes;
+use crate::error::Error;
+use crate::header::{split, Header};
+
+/// Borrowed view of an encoded item for random access without decoding all of it
+///
+/// Only the headers of the items walked over are parsed and nothing is allocated,
+/// so reading one field of a large object stays cheap.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RlpView<'a> {
+    raw: &'a [u8],
+    header_len: usize,
+    is_list: bool,
+}
+
+impl<'a> RlpView<'a> {
+    /// View of the item at the start of `bytes`, anything after it is ignored
+    pub fn new(bytes: &'a [u8]) -> Result<Self, Error> {
+        let (Header { list, .. }, payload, rest) = split(bytes)?;
+        Ok(RlpView {
+            raw: &bytes[..bytes.len() - rest.len()],
+            header_len: bytes.len() - rest.len() - payload.len(),
+            is_list: list,
+        })
+    }
+
+    pub fn is_list(&self) -> bool {
+        self.is_list
+    }
+
+    /// The encoded item, header included
+    pub fn as_raw(&self) -> &'a [u8] {
+        self.raw
+    }
+
+    /// Contents of a byte string
+    pub fn data(&self) -> Result<&'a [u8], Error> {
+        if self.is_list {
+            return Err(Error::ExpectedString);
+        }
+        Ok(self.payload())
+    }
+
+    /// Number of items in a list
+    pub fn item_count(&self) -> Result<usize, Error> {
+        if !self.is_list {
+            return Err(Error::ExpectedList);
+        }
+        self.iter()
+            .try_fold(0, |count, item| item.map(|_| count + 1))
+    }
+
+    /// Item at `index` of a list
+    pub fn at(&self, index: usize) -> Result<RlpView<'a>, Error> {
+        if !self.is_list {
+            return Err(Error::ExpectedList);
+        }
+        self.iter()
+            .nth(index)
+            .unwrap_or(Err(Error::IndexOutOfBounds(index)))
+    }
+
+    /// Items of a list, a byte string has none
+    pub fn iter(&self) -> RlpViewIter<'a> {
+        let rest = if self.is_list { self.payload() } else { &[] };
+        RlpViewIter { rest }
+    }
+
+    pub fn decode<T>(&self) -> Result<T, Error>
+    where
+        T: Deserialize<'a>,
+    {
+        from_rlp_bytes(self.raw)
+    }
+
+    fn payload(&self) -> &'a [u8] {
+        &self.raw[self.header_len..]
+    }
+}
+
+/// Iterator over the items of a list `RlpView`
+pub struct RlpViewIter<'a> {
+    rest: &'a [u8],
+}
+
+impl<'a> Iterator for RlpViewIter<'a> {
+    type Item = Result<RlpView<'a>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.rest.is_empty() {
+            return None;
+        }
+        match RlpView::new(self.rest) {
+            Ok(view) => {
+                self.rest = &self.rest[view.raw.len()..];
+                Some(Ok(view))
+            }
+            Err(err) => {
+                // A malformed item ends the iteration
+                self.rest = &[];
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+impl<'a> IntoIterator for RlpView<'a> {
+    type Item = Result<RlpView<'a>, Error>;
+    type IntoIter = RlpViewIter<'a>;
+
+    fn into_iter(self) -> RlpViewIter<'a> {
+        self.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ser::to_rlp_bytes;
+    use serde::Serialize;
+
+    #[derive(Serialize)]
+    struct Body {
+        number: u64,
+        extra: String,
+        transactions: Vec<Vec<u8>>,
+    }
+
+    #[test]
+    fn view_test() {
+        let body = Body {
+            number: 1024,
+            extra: String::from("cat"),
+            transactions: vec![vec![1], vec![2, 3], vec![]],
+        };
+        let bytes = to_rlp_bytes(&body).unwrap();
+
+        let view = RlpView::new(&bytes).unwrap();
+        assert!(view.is_list());
+        assert_eq!(view.item_count().unwrap(), 3);
+        assert_eq!(view.at(0).unwrap().decode::<u64>().unwrap(), 1024);
+        assert_eq!(view.at(1).unwrap().data().unwrap(), b"cat");
+        assert_eq!(view.at(2).unwrap().item_count().unwrap(), 3);
+        assert_eq!(
+            view.at(2).unwrap().at(1).unwrap().as_raw(),
+            [0xc2, 0x02, 0x03]
+        );
+        assert!(matches!(view.at(3), Err(Error::IndexOutOfBounds(3))));
+
+        let lens: Vec<usize> = view
+            .iter()
+            .map(|item| item.unwrap().as_raw().len())
+            .collect();
+        assert_eq!(lens, [3, 4, 7]);
+    }
+}